@@ -1,36 +1,146 @@
 
-use std::{cell::{Ref, RefCell, RefMut}, collections::{BTreeSet, HashMap, HashSet}, hash::RandomState, vec};
+use std::collections::HashMap;
 
-use crate::{parse::{Ast, Node, NodeIndex}, token::TokenStream};
+use crate::{eval::{self, Value}, parse::{Ast, Node, NodeIndex, NodeMap, Pattern}, report::Report, token::{split_suffix, Span, Token, TokenStream, TT}};
 
 
-#[derive(Debug, Clone)]
-enum Type {
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
 	Var(u32),
+	Unit,
+	/// a 64 bit signed integer, also spelled `i64`
 	Int,
+	/// an integer of a specific size, like `u8` or `i32`
+	Sized {
+		signed: bool,
+		bits: u8,
+	},
+	Float,
 	Bool,
+	Tuple(Vec<TypeIndex>),
+	/// a structural record, with fields sorted by name
+	Record(Vec<(String, TypeIndex)>),
+	/// a value that may be `nil`, written `T?`
+	Option(TypeIndex),
+	/// an array of elements of one type, written `[T]`
+	Array(TypeIndex),
 	Fn(TypeIndex, TypeIndex),
+	/// a type declared with `enum`, which is only ever the same as itself
+	Enum(String),
+}
+
+/// a [`Type`] with its parts nested in it rather than behind indices, so it
+/// makes sense on its own. unbound variables are numbered as in the pool
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolvedType {
+	Var(u32),
+	Unit,
+	Int,
+	Sized {
+		signed: bool,
+		bits: u8,
+	},
+	Float,
+	Bool,
+	Tuple(Vec<ResolvedType>),
+	Record(Vec<(String, ResolvedType)>),
+	Option(Box<ResolvedType>),
+	Array(Box<ResolvedType>),
+	Fn(Box<ResolvedType>, Box<ResolvedType>),
+	Enum(String),
 }
 
 #[repr(transparent)]
 #[derive(Debug, Clone, Copy, PartialEq)]
-struct TypeIndex (u32);
+pub struct TypeIndex (u32);
+
+// the types that are always in scope, by name
+fn builtin_type(name: &str) -> Option<Type> {
+	Some(match name {
+		"Unit" => Type::Unit,
+		"Int" => Type::Int,
+		"Float" => Type::Float,
+		"Bool" => Type::Bool,
+		"i64" => Type::Int,
+		"i8" => Type::Sized { signed: true, bits: 8 },
+		"i16" => Type::Sized { signed: true, bits: 16 },
+		"i32" => Type::Sized { signed: true, bits: 32 },
+		"u8" => Type::Sized { signed: false, bits: 8 },
+		"u16" => Type::Sized { signed: false, bits: 16 },
+		"u32" => Type::Sized { signed: false, bits: 32 },
+		"u64" => Type::Sized { signed: false, bits: 64 },
+		// every float is 64 bits, so `f32` marks a float without narrowing it
+		"f32" | "f64" => Type::Float,
+		_ => return None,
+	})
+}
+
+#[derive(Debug, Clone)]
+pub struct BindOptions {
+	/// the type given to integer literals. this should be a type without
+	/// parameters, such as [`Type::Int`] or [`Type::Float`]
+	pub default_int: Type,
+	/// extra binary operators, checked before the built-in rules
+	pub operators: Vec<Operator>,
+	/// warn on float literals that get rounded, like `0.1`, or that are
+	/// subnormal. literals too large for a float are an error either way
+	pub warn_float_precision: bool,
+	/// report warnings as errors
+	pub strict: bool,
+}
+impl Default for BindOptions {
+	fn default() -> Self {
+		Self {
+			default_int: Type::Int,
+			operators: Vec::new(),
+			warn_float_precision: false,
+			strict: false,
+		}
+	}
+}
+
+/// a binary operator over two operand types. like
+/// [`BindOptions::default_int`], the types should be without parameters
+#[derive(Debug, Clone)]
+pub struct Operator {
+	pub op: TT,
+	pub left: Type,
+	pub right: Type,
+	pub result: Type,
+}
 
 
 
 
+/// a type with some of its variables quantified over. every use of a
+/// binding with a scheme gets its own copy of the quantified variables
+#[derive(Debug, Clone)]
+struct Scheme {
+	vars: Vec<TypeIndex>,
+	ty: TypeIndex,
+}
+impl Scheme {
+	fn mono(ty: TypeIndex) -> Self {
+		Self { vars: Vec::new(), ty }
+	}
+}
+
 #[derive(Debug)]
 struct Bindings<'a> {
 	pool: Vec<Type>,
-	count: u32,
-	map: Vec<HashMap<&'a str, Option<TypeIndex>>>,
+	map: Vec<HashMap<&'a str, Option<Scheme>>>,
+	// type aliases in scope, or `None` while an alias's own type is resolved
+	aliases: Vec<HashMap<&'a str, Option<TypeIndex>>>,
+	// the values of the constants in scope
+	consts: Vec<HashMap<&'a str, Value>>,
 }
 impl<'a> Bindings<'a> {
 	fn new() -> Self {
 		Self {
 			pool: vec![],
-			count: 0,
 			map: vec![HashMap::new()],
+			aliases: vec![HashMap::new()],
+			consts: vec![HashMap::new()],
 		}
 	}
 
@@ -42,71 +152,1405 @@ impl<'a> Bindings<'a> {
 	}
 	fn scope_begin(&mut self) {
 		self.map.push(self.map.last().cloned().unwrap());
+		self.aliases.push(self.aliases.last().cloned().unwrap());
+		self.consts.push(self.consts.last().cloned().unwrap());
 	}
 	fn scope_end(&mut self) {
 		self.map.pop();
+		self.aliases.pop();
+		self.consts.pop();
+	}
+
+	fn add(&mut self, ty: Type) -> TypeIndex {
+		self.pool.push(ty);
+		TypeIndex(self.pool.len() as u32 - 1)
+	}
+
+	// a variable is unbound while it points at itself, and is bound by
+	// pointing it at another slot in the pool
+	fn fresh(&mut self) -> TypeIndex {
+		let index = self.pool.len() as u32;
+		self.add(Type::Var(index))
+	}
+
+	fn find(&self, mut index: TypeIndex) -> TypeIndex {
+		while let Type::Var(next) = self.get(index) {
+			if *next == index.0 {
+				break;
+			}
+			index = TypeIndex(*next);
+		}
+		index
+	}
+
+	fn define(&mut self, name: &'a str, ty: TypeIndex) {
+		// a new binding shadows any constant of the same name
+		self.consts.last_mut().unwrap().remove(name);
+		self.map.last_mut().unwrap().insert(name, Some(Scheme::mono(ty)));
+	}
+	fn lookup(&mut self, name: &str) -> Option<TypeIndex> {
+		let scheme = self.scheme(name)?.clone();
+		Some(self.instantiate(&scheme))
+	}
+	fn scheme(&self, name: &str) -> Option<&Scheme> {
+		self.map.last().unwrap().get(name).and_then(|v| v.as_ref())
+	}
+
+	/// defines a binding, quantifying over every variable in its type that
+	/// isn't still free somewhere in the surrounding scope
+	fn generalize(&mut self, name: &'a str, ty: TypeIndex) {
+		let mut env = Vec::new();
+		for scheme in self.map.last().unwrap().values().flatten() {
+			let mut vars = Vec::new();
+			self.free(scheme.ty, &mut vars);
+			env.extend(vars.into_iter().filter(|v| !scheme.vars.contains(v)));
+		}
+
+		let mut vars = Vec::new();
+		self.free(ty, &mut vars);
+		vars.retain(|v| !env.contains(v));
+		self.consts.last_mut().unwrap().remove(name);
+		self.map.last_mut().unwrap().insert(name, Some(Scheme { vars, ty }));
+	}
+
+	// collects the unbound variables in a type, in the order they appear
+	fn free(&self, index: TypeIndex, out: &mut Vec<TypeIndex>) {
+		let index = self.find(index);
+		match self.get(index) {
+			Type::Var(_) if !out.contains(&index) => out.push(index),
+			Type::Tuple(items) => items.iter().for_each(|v| self.free(*v, out)),
+			Type::Record(fields) => fields.iter().for_each(|(_, v)| self.free(*v, out)),
+			Type::Option(inner) | Type::Array(inner) => self.free(*inner, out),
+			Type::Fn(args, ret) => {
+				self.free(*args, out);
+				self.free(*ret, out);
+			}
+			_ => (),
+		}
+	}
+
+	fn instantiate(&mut self, scheme: &Scheme) -> TypeIndex {
+		if scheme.vars.is_empty() {
+			return scheme.ty;
+		}
+		let map = scheme.vars.iter().map(|v| (*v, self.fresh())).collect::<Vec<_>>();
+		self.substitute(scheme.ty, &map)
+	}
+
+	fn substitute(&mut self, index: TypeIndex, map: &[(TypeIndex, TypeIndex)]) -> TypeIndex {
+		let index = self.find(index);
+		if let Some((_, to)) = map.iter().find(|(from, _)| *from == index) {
+			return *to;
+		}
+		match self.get(index).clone() {
+			Type::Tuple(items) => {
+				let items = items.into_iter().map(|v| self.substitute(v, map)).collect();
+				self.add(Type::Tuple(items))
+			}
+			Type::Record(fields) => {
+				let fields = fields.into_iter().map(|(name, v)| (name, self.substitute(v, map))).collect();
+				self.add(Type::Record(fields))
+			}
+			Type::Option(inner) => {
+				let inner = self.substitute(inner, map);
+				self.add(Type::Option(inner))
+			}
+			Type::Array(inner) => {
+				let inner = self.substitute(inner, map);
+				self.add(Type::Array(inner))
+			}
+			Type::Fn(args, ret) => {
+				let args = self.substitute(args, map);
+				let ret = self.substitute(ret, map);
+				self.add(Type::Fn(args, ret))
+			}
+			_ => index,
+		}
+	}
+
+	// a copy of a type with every index in it pointing at its representative
+	fn shallow(&self, index: TypeIndex) -> Type {
+		match self.get(self.find(index)).clone() {
+			Type::Tuple(items) => Type::Tuple(items.into_iter().map(|v| self.find(v)).collect()),
+			Type::Record(fields) => Type::Record(fields.into_iter().map(|(name, v)| (name, self.find(v))).collect()),
+			Type::Option(inner) => Type::Option(self.find(inner)),
+			Type::Array(inner) => Type::Array(self.find(inner)),
+			Type::Fn(args, ret) => Type::Fn(self.find(args), self.find(ret)),
+			other => other,
+		}
+	}
+
+	/// a type with every part of it looked up, all the way down
+	fn resolve(&self, index: TypeIndex) -> ResolvedType {
+		let index = self.find(index);
+		match self.get(index) {
+			Type::Var(_) => ResolvedType::Var(index.0),
+			Type::Unit => ResolvedType::Unit,
+			Type::Int => ResolvedType::Int,
+			Type::Sized { signed, bits } => ResolvedType::Sized { signed: *signed, bits: *bits },
+			Type::Float => ResolvedType::Float,
+			Type::Bool => ResolvedType::Bool,
+			Type::Tuple(items) => ResolvedType::Tuple(items.iter().map(|v| self.resolve(*v)).collect()),
+			Type::Record(fields) => ResolvedType::Record(fields.iter().map(|(name, v)| (name.clone(), self.resolve(*v))).collect()),
+			Type::Option(inner) => ResolvedType::Option(Box::new(self.resolve(*inner))),
+			Type::Array(inner) => ResolvedType::Array(Box::new(self.resolve(*inner))),
+			Type::Fn(args, ret) => ResolvedType::Fn(Box::new(self.resolve(*args)), Box::new(self.resolve(*ret))),
+			Type::Enum(name) => ResolvedType::Enum(name.clone()),
+		}
+	}
+
+	fn is(&self, index: TypeIndex, ty: &Type) -> bool {
+		self.get(self.find(index)) == ty
+	}
+
+	fn is_numeric(&self, index: TypeIndex) -> bool {
+		matches!(self.get(self.find(index)), Type::Int | Type::Sized { .. } | Type::Float)
+	}
+
+	fn occurs(&self, var: TypeIndex, index: TypeIndex) -> bool {
+		let index = self.find(index);
+		if index == var {
+			return true;
+		}
+		match self.get(index) {
+			Type::Tuple(items) => items.iter().any(|v| self.occurs(var, *v)),
+			Type::Record(fields) => fields.iter().any(|(_, v)| self.occurs(var, *v)),
+			Type::Option(inner) | Type::Array(inner) => self.occurs(var, *inner),
+			Type::Fn(args, ret) => self.occurs(var, *args) || self.occurs(var, *ret),
+			_ => false,
+		}
+	}
+
+	fn unify(&mut self, a: TypeIndex, b: TypeIndex) -> bool {
+		let a = self.find(a);
+		let b = self.find(b);
+		if a == b {
+			return true;
+		}
+
+		match (self.get(a).clone(), self.get(b).clone()) {
+			(Type::Var(_), _) => {
+				if self.occurs(a, b) {
+					return false;
+				}
+				*self.get_mut(a) = Type::Var(b.0);
+				true
+			}
+			(_, Type::Var(_)) => self.unify(b, a),
+
+			(Type::Unit, Type::Unit)
+			| (Type::Int, Type::Int)
+			| (Type::Float, Type::Float)
+			| (Type::Bool, Type::Bool) => true,
+
+			(Type::Sized { signed: x_signed, bits: x_bits }, Type::Sized { signed: y_signed, bits: y_bits }) => {
+				x_signed == y_signed && x_bits == y_bits
+			}
+			(Type::Enum(x), Type::Enum(y)) => x == y,
+
+			(Type::Tuple(x), Type::Tuple(y)) => {
+				x.len() == y.len()
+				&& x.iter().zip(y.iter()).all(|(x, y)| self.unify(*x, *y))
+			}
+			(Type::Record(x), Type::Record(y)) => {
+				x.len() == y.len()
+				&& x.iter().zip(y.iter()).all(|((x_name, x), (y_name, y))| x_name == y_name && self.unify(*x, *y))
+			}
+			(Type::Option(x), Type::Option(y))
+			| (Type::Array(x), Type::Array(y)) => self.unify(x, y),
+			(Type::Fn(x_args, x_ret), Type::Fn(y_args, y_ret)) => {
+				self.unify(x_args, y_args) && self.unify(x_ret, y_ret)
+			}
+
+			_ => false,
+		}
+	}
+
+	fn display(&self, index: TypeIndex) -> String {
+		self.display_all(&[index]).remove(0)
+	}
+
+	/// displays several types that go in one message. unbound variables are
+	/// numbered `?1`, `?2`, ... in the order they first appear across all of
+	/// them, so a message doesn't change with how many variables inference
+	/// happened to make along the way
+	fn display_all(&self, indices: &[TypeIndex]) -> Vec<String> {
+		let mut vars = Vec::new();
+		for index in indices {
+			self.free(*index, &mut vars);
+		}
+		let names = vars.into_iter().enumerate()
+			.map(|(i, v)| (v, format!("?{}", i + 1)))
+			.collect::<Vec<_>>();
+		indices.iter().map(|v| self.display_with(*v, &names)).collect()
+	}
+
+	/// displays a scheme, naming its quantified variables `a`, `b`, ... in
+	/// the order they appear, as in `forall a. (a) -> a`
+	fn display_scheme(&self, scheme: &Scheme) -> String {
+		if scheme.vars.is_empty() {
+			return self.display(scheme.ty);
+		}
+		let names = scheme.vars.iter().enumerate()
+			.map(|(i, v)| {
+				let letter = (b'a' + (i % 26) as u8) as char;
+				match i / 26 {
+					0 => (*v, letter.to_string()),
+					n => (*v, format!("{}{}", letter, n)),
+				}
+			})
+			.collect::<Vec<_>>();
+		let quantified = names.iter().map(|(_, v)| v.as_str()).collect::<Vec<_>>();
+		format!("forall {}. {}", quantified.join(" "), self.display_with(scheme.ty, &names))
+	}
+
+	/// spells a type as it would be written in an annotation, if the
+	/// annotation syntax can express it
+	fn annotation(&self, index: TypeIndex) -> Option<String> {
+		match self.get(self.find(index)) {
+			Type::Unit | Type::Int | Type::Float | Type::Bool | Type::Sized { .. } | Type::Enum(_) => Some(self.display(index)),
+			Type::Option(inner) => Some(format!("{}?", self.annotation(*inner)?)),
+			Type::Array(inner) => Some(format!("[{}]", self.annotation(*inner)?)),
+			_ => None,
+		}
+	}
+
+	fn display_with(&self, index: TypeIndex, names: &[(TypeIndex, String)]) -> String {
+		let index = self.find(index);
+		match self.get(index) {
+			Type::Var(v) => match names.iter().find(|(var, _)| *var == index) {
+				Some((_, name)) => name.clone(),
+				None => format!("?{}", v),
+			},
+			Type::Unit => "Unit".to_string(),
+			Type::Int => "Int".to_string(),
+			Type::Float => "Float".to_string(),
+			Type::Bool => "Bool".to_string(),
+			Type::Sized { signed, bits } => format!("{}{}", if *signed { "i" } else { "u" }, bits),
+			Type::Tuple(items) => {
+				let items = items.iter().map(|v| self.display_with(*v, names)).collect::<Vec<_>>();
+				format!("({})", items.join(", "))
+			}
+			Type::Record(fields) => {
+				let fields = fields.iter().map(|(name, v)| format!("{}: {}", name, self.display_with(*v, names))).collect::<Vec<_>>();
+				format!("{{ {} }}", fields.join(", "))
+			}
+			Type::Option(inner) => format!("{}?", self.display_with(*inner, names)),
+			Type::Array(inner) => format!("[{}]", self.display_with(*inner, names)),
+			Type::Fn(args, ret) => format!("{} -> {}", self.display_with(*args, names), self.display_with(*ret, names)),
+			Type::Enum(name) => name.clone(),
+		}
 	}
 }
 
-struct Tst<'a> {
+/// a typed syntax tree, an [`Ast`] with the type inferred for each node.
+///
+/// nodes in type position, like the `Int` in `let x: Int = 0`, have no type.
+/// type variables left unbound by inference stay as [`Type::Var`], since
+/// they're generic
+#[derive(Debug)]
+pub struct Tst<'a> {
 	pub tokens: &'a TokenStream<'a>,
 	pub nodes: Vec<Node<'a>>,
+	/// the span of each node, parallel to `nodes`
+	pub spans: Vec<Span>,
 	pub root: NodeIndex,
-	pub types: Vec<Type>,
+	pub types: NodeMap<Type>,
+	indices: NodeMap<TypeIndex>,
+	bindings: Bindings<'a>,
 }
 impl<'a> Tst<'a> {
-	pub fn get(&self, node: &NodeIndex) -> &Node {
+	pub fn get(&self, node: &NodeIndex) -> &Node<'a> {
 		&self.nodes[node.0 as usize]
 	}
+
+	/// the type of a node. types nested inside it, like a function's
+	/// parameters, are shown with [`Tst::display`]
+	pub fn ty(&self, node: &NodeIndex) -> Option<&Type> {
+		self.types.get(node)
+	}
+
+	pub fn display(&self, node: &NodeIndex) -> Option<String> {
+		self.indices.get(node).map(|v| self.bindings.display(*v))
+	}
+
+	/// the source with inferred types written in wherever a `let` binding or
+	/// function signature leaves them out, so `let x = 1` becomes
+	/// `let x: Int = 1`. types annotations can't express, like functions or
+	/// ones that are still generic, are left out
+	pub fn annotated(&self) -> String {
+		let src: &str = &self.tokens.src;
+		let mut edits = Vec::new();
+
+		for (i, node) in self.nodes.iter().enumerate() {
+			match node {
+				Node::Let { pattern: Pattern::Identifier { name }, expr, annotation: None, .. } => {
+					if let Some(ty) = self.indices.get(expr).and_then(|v| self.bindings.annotation(*v)) {
+						edits.push((name.span().end, format!(": {}", ty)));
+					}
+				}
+				Node::Fn { args, ret, where_clause, expr, .. } => {
+					let Some(&ty) = self.indices.get(&NodeIndex(i as u32)) else {
+						continue;
+					};
+					let Type::Fn(params, result) = self.bindings.get(self.bindings.find(ty)) else {
+						continue;
+					};
+					let Type::Tuple(params) = self.bindings.get(self.bindings.find(*params)) else {
+						continue;
+					};
+
+					for ((name, annotation), param) in args.iter().zip(params) {
+						if annotation.is_none() && let Some(ty) = self.bindings.annotation(*param) {
+							edits.push((name.span().end, format!(": {}", ty)));
+						}
+					}
+
+					// the return type goes after the `)`, before any where clause
+					let before = match where_clause {
+						Some(where_clause) => self.spans[where_clause.0 as usize].start,
+						None => self.tokens.iter()
+							.rfind(|v| v.kind == TT::EqualGreater && v.span().end <= self.spans[expr.0 as usize].start)
+							.map_or(0, |v| v.span().start),
+					};
+					let after = self.tokens.iter()
+						.rfind(|v| !v.kind.is_trivia() && v.kind != TT::Eof && v.span().end <= before);
+					if ret.is_none() && let (Some(after), Some(ty)) = (after, self.bindings.annotation(*result)) {
+						edits.push((after.span().end, format!(": {}", ty)));
+					}
+				}
+				_ => (),
+			}
+		}
+
+		edits.sort_by_key(|(at, _)| *at);
+		let mut out = String::new();
+		let mut last = 0;
+		for (at, text) in edits {
+			out.push_str(&src[last..at as usize]);
+			out.push_str(&text);
+			last = at as usize;
+		}
+		out.push_str(&src[last..]);
+		out
+	}
+}
+
+// a loop the walk is inside, as far as `break` and `continue` are concerned
+#[derive(Debug)]
+struct Loop<'a> {
+	label: Option<&'a str>,
+	// the type of the values it's broken out with. only `loop` has one, the
+	// other loops always finish as Unit
+	value: Option<TypeIndex>,
+	broken: bool,
 }
 
+#[derive(Debug)]
 struct Annotate<'a> {
-	ast: Ast<'a>,
-	types: Vec<Option<Type>>,
+	src: &'a str,
+	ast: &'a Ast<'a>,
+	options: BindOptions,
+	bindings: Bindings<'a>,
+	types: NodeMap<TypeIndex>,
+	// how many function bodies the walk is currently inside
+	functions: u32,
+	// the loops the walk is currently inside, innermost last
+	loops: Vec<Loop<'a>>,
+	report: Report,
 }
 impl<'a> Annotate<'a> {
-	fn new(ast: Ast<'a>) -> Self {
+	fn new(src: &'a str, ast: &'a Ast<'a>, options: BindOptions) -> Self {
+		let mut report = Report::new();
+		report.set_strict(options.strict);
 		Self {
-			types: vec![None; ast.nodes.len()],
+			src,
+			options,
+			types: NodeMap::new(),
+			bindings: Bindings::new(),
+			functions: 0,
+			loops: Vec::new(),
+			report,
 			ast,
 		}
 	}
 
-	fn impost(&mut self, index: NodeIndex, ) {
-		
+	// the type a number literal's suffix names, as in `10i64`. a suffix for
+	// the wrong kind of number is reported
+	fn suffix(&mut self, token: &Token) -> Option<Type> {
+		let text = token.get(self.src);
+		let suffix = split_suffix(text).1;
+		// the lexer only accepts the suffixes in `SUFFIXES`, which all name
+		// a type, so this is only `None` without one
+		let ty = builtin_type(suffix)?;
+		let float = matches!(ty, Type::Float);
+		match (token.kind, float) {
+			(TT::Integer, true) => {
+				self.report.error_at(token.span(), format!("float suffix '{}' on integer literal '{}'", suffix, text));
+				None
+			}
+			(TT::Float, false) => {
+				self.report.error_at(token.span(), format!("integer suffix '{}' on float literal '{}'", suffix, text));
+				None
+			}
+			_ => Some(ty),
+		}
+	}
+
+	fn loop_body(&mut self, label: Option<&'a Token>, value: Option<TypeIndex>, body: &NodeIndex) {
+		self.loops.push(Loop { label: label.map(|v| v.get(self.src)), value, broken: false });
+		self.annotate(body, false);
+		let done = self.loops.pop().unwrap();
+		if let Some(ty) = done.value.filter(|_| !done.broken) {
+			let unit = self.bindings.add(Type::Unit);
+			self.expect(body, ty, unit);
+		}
+	}
+
+	// checks a `break`'s value against the loop it breaks out of. a `break`
+	// without a value leaves a `loop` with Unit
+	fn break_value(&mut self, op: &Token, target: Option<usize>, value: Option<&NodeIndex>) {
+		let Some(target) = target else {
+			if let Some(value) = value {
+				self.annotate(value, true);
+			}
+			return;
+		};
+		self.loops[target].broken = true;
+		match (self.loops[target].value, value) {
+			(Some(ty), Some(value)) => {
+				self.impost(value, ty);
+			}
+			(Some(ty), None) => {
+				let unit = self.bindings.add(Type::Unit);
+				self.expect_at(op.span(), unit, ty);
+			}
+			(None, Some(value)) => {
+				self.report.error_at(op.span(), "only 'loop' can be broken out of with a value".to_string());
+				self.annotate(value, true);
+			}
+			(None, None) => (),
+		}
+	}
+
+	// reports a float literal that doesn't survive becoming an f64
+	fn float(&mut self, token: &Token) {
+		let text = token.get(self.src);
+		let Some(value) = self.ast.tokens.float_value(token) else {
+			return;
+		};
+		if value.is_infinite() {
+			self.report.error_at(token.span(), format!("float literal '{}' is too large", text));
+		} else if !self.options.warn_float_precision {
+			// the rest are only warnings, and only when asked for
+		} else if value.is_subnormal() {
+			self.report.warn_at(token.span(), format!("float literal '{}' is subnormal", text));
+		} else if !exact_float(split_suffix(text).0, value) {
+			self.report.warn_at(token.span(), format!("float literal '{}' can't be represented exactly", text));
+		}
+	}
+
+	/// checks a node against an expected type. the expectation is pushed
+	/// down into the node's children where possible, so a mismatch is
+	/// reported at the innermost node that causes it
+	fn impost(&mut self, index: &NodeIndex, expected: TypeIndex) -> TypeIndex {
+		let ty = match self.ast.get(index) {
+			Node::Group { expr } => self.impost(expr, expected),
+
+			// an integer literal is valid for any numeric type, unless its
+			// suffix already picked one
+			Node::Integer { value } if split_suffix(value.get(self.src)).1.is_empty() && matches!(
+				self.bindings.get(self.bindings.find(expected)),
+				Type::Float | Type::Sized { .. },
+			) => {
+				expected
+			}
+
+			// a negated number is whatever number is expected, as in
+			// `let x: i8 = -1`
+			Node::Unary { op, right } if op.kind == TT::Minus && self.bindings.is_numeric(expected) => {
+				self.impost(right, expected)
+			}
+
+			Node::Block { expr } if !expr.is_empty() => {
+				self.bindings.scope_begin();
+				let (last, rest) = expr.split_last().unwrap();
+				for v in rest {
+					self.annotate(v, false);
+				}
+				let ty = self.impost(last, expected);
+				self.bindings.scope_end();
+				ty
+			}
+
+			// so elements are checked against the expected element type, as in
+			// `let xs: [Float] = [1, 2]`
+			Node::Array { items } => match *self.bindings.get(self.bindings.find(expected)) {
+				Type::Array(elem) => {
+					for v in items {
+						self.impost(v, elem);
+					}
+					expected
+				}
+				_ => {
+					let ty = self.annotate(index, true);
+					self.expect(index, ty, expected);
+					return ty;
+				}
+			},
+
+			Node::If { else_branch: Some(_), .. } => self.if_chain(index, true, Some(expected)),
+
+			_ => {
+				let ty = self.annotate(index, true);
+				self.expect(index, ty, expected);
+				return ty;
+			}
+		};
+
+		self.types.insert(index, ty);
+		ty
+	}
+
+	// runs inference over the whole tree, and resolves each node's type
+	fn build(mut self) -> (Tst<'a>, Report) {
+		// a walk over the whole tree may have been done already, and doing it
+		// again would report everything twice
+		if self.types.get(&self.ast.root).is_none() {
+			self.annotate(&self.ast.root, false);
+			self.literals();
+		}
+
+		let mut types = NodeMap::new();
+		for (index, ty) in self.types.iter() {
+			types.insert(&index, self.bindings.shallow(*ty));
+		}
+		let tst = Tst {
+			tokens: self.ast.tokens,
+			nodes: self.ast.nodes.clone(),
+			spans: self.ast.spans.clone(),
+			root: self.ast.root.clone(),
+			types,
+			indices: self.types,
+			bindings: self.bindings,
+		};
+		(tst, self.report)
+	}
+
+	// unifies a node's type with the one expected of it, reporting a
+	// mismatch at the node
+	fn expect(&mut self, index: &NodeIndex, ty: TypeIndex, expected: TypeIndex) {
+		self.expect_at(self.ast.span(index), ty, expected);
 	}
 
-	fn build(mut self) -> Tst<'a> {
-		todo!()
+	fn expect_at(&mut self, span: Span, ty: TypeIndex, expected: TypeIndex) {
+		if !self.bindings.unify(expected, ty) {
+			let types = self.bindings.display_all(&[expected, ty]);
+			self.report.error_at(span, format!("type mismatch: expected '{}', found '{}'", types[0], types[1]));
+		}
+	}
+
+	// whether a node is an integer literal without a suffix, whose type is
+	// whatever `impost` expects of it. looks through the same nodes `impost`
+	// pushes an expectation into
+	fn literal(&self, index: &NodeIndex) -> bool {
+		match self.ast.get(index) {
+			Node::Group { expr } => self.literal(expr),
+			Node::Unary { op, right } if op.kind == TT::Minus => self.literal(right),
+			Node::Integer { value } => split_suffix(value.get(self.src)).1.is_empty(),
+			Node::Block { expr } => expr.last().is_some_and(|v| self.literal(v)),
+			Node::If { then_branch, else_branch: Some(else_branch), .. } => {
+				self.literal(then_branch) || self.literal(else_branch)
+			}
+			_ => false,
+		}
+	}
+
+	// whether a node is a literal zero, looking through groups
+	fn is_zero(&self, index: &NodeIndex) -> bool {
+		match self.ast.get(index) {
+			Node::Group { expr } => self.is_zero(expr),
+			Node::Integer { value } => self.ast.tokens.int_value(value) == Some(0),
+			Node::Float { value } => self.ast.tokens.float_value(value) == Some(0.0),
+			_ => false,
+		}
+	}
+
+	// checks every integer literal fits the type it was finally inferred as.
+	// this runs after the walk, since a literal's type may be decided by a
+	// later use
+	fn literals(&mut self) {
+		// literals under a unary `-`, looking through groups
+		let mut negated = NodeMap::new();
+		for node in &self.ast.nodes {
+			let Node::Unary { op, right } = node else {
+				continue;
+			};
+			let mut right = right;
+			while let Node::Group { expr } = self.ast.get(right) {
+				right = expr;
+			}
+			if op.kind == TT::Minus {
+				negated.insert(right, ());
+			}
+		}
+
+		for (index, &ty) in self.types.iter() {
+			let Node::Integer { value } = self.ast.get(&index) else {
+				continue;
+			};
+			// a signed type's minimum is one further from zero than its
+			// maximum, so a negated literal can be one larger
+			let negated = negated.get(&index).is_some();
+			let max = match (self.bindings.get(self.bindings.find(ty)), negated) {
+				(Type::Sized { signed: false, .. }, true) => 0,
+				(Type::Int, _) => i64::MAX as u128 + negated as u128,
+				(Type::Sized { signed: true, bits }, _) => (1 << (bits - 1)) - 1 + negated as u128,
+				(Type::Sized { signed: false, bits }, false) => (1 << bits) - 1,
+				_ => continue,
+			};
+			let fits = self.ast.tokens.int_value(value).is_some_and(|v| v <= max);
+			if !fits {
+				self.report.error_at(
+					value.span(),
+					format!("integer literal out of range for '{}'", self.bindings.display(ty)),
+				);
+			}
+		}
+	}
+
+	// resolves a binary operator against its operand types, trying the
+	// registered operators first and then the built-in rules
+	fn binary(&mut self, op: &Token, left: &NodeIndex, right: &NodeIndex) -> TypeIndex {
+		let comparison = matches!(
+			op.kind,
+			TT::EqualEqual | TT::BangEqual | TT::Lesser | TT::LesserEqual | TT::Greater | TT::GreaterEqual,
+		);
+
+		let left = self.annotate(left, true);
+		let registered = self.options.operators.iter()
+			.any(|v| v.op == op.kind && self.bindings.is(left, &v.left));
+
+		// numbers keep the expectation on a right operand that takes its
+		// type from it, so the literal in `x + 1` takes the type of `x`
+		if !registered && self.bindings.is_numeric(left) && self.literal(right) {
+			self.impost(right, left);
+			return if comparison { self.bindings.add(Type::Bool) } else { left };
+		}
+
+		let right = self.annotate(right, true);
+		let found = self.options.operators.iter()
+			.find(|v| v.op == op.kind && self.bindings.is(left, &v.left) && self.bindings.is(right, &v.right));
+		if let Some(operator) = found {
+			let result = operator.result.clone();
+			return self.bindings.add(result);
+		}
+
+		// numbers of the same type have every operator
+		if self.bindings.is_numeric(left) && self.bindings.is_numeric(right) && self.bindings.unify(left, right) {
+			return if comparison { self.bindings.add(Type::Bool) } else { left };
+		}
+
+		// operands of an unknown type are inferred to match, and any two
+		// values of the same type can be compared for equality
+		let unknown = |ty| matches!(self.bindings.get(self.bindings.find(ty)), Type::Var(_));
+		let allowed = match op.kind {
+			TT::EqualEqual | TT::BangEqual => true,
+			_ => unknown(left) || unknown(right),
+		};
+		if allowed && self.bindings.unify(left, right) {
+			// arithmetic needs numbers, so operands that are still unknown,
+			// like the parameters in `fn (a, b) => a + b`, get the same
+			// type an integer literal would
+			let arithmetic = matches!(op.kind, TT::Plus | TT::Minus | TT::Star | TT::Slash | TT::Percent);
+			if arithmetic && matches!(self.bindings.get(self.bindings.find(left)), Type::Var(_)) {
+				let default = self.bindings.add(self.options.default_int.clone());
+				self.bindings.unify(left, default);
+			}
+			return if comparison { self.bindings.add(Type::Bool) } else { left };
+		}
+
+		let types = self.bindings.display_all(&[left, right]);
+		self.report.error_at(op.span(), format!(
+			"no operator '{}' for '{}' and '{}'",
+			op.kind.symbol().unwrap_or_default(), types[0], types[1],
+		));
+		self.bindings.fresh()
+	}
+
+	// an `else if` chain is walked in a loop rather than by recursing into
+	// each `else`, so a long chain doesn't run out of stack. every `if` in
+	// the chain has the same type, unless the chain is a statement without
+	// a final `else`, where the branches have nothing to agree on
+	fn if_chain(&mut self, index: &NodeIndex, value: bool, expected: Option<TypeIndex>) -> TypeIndex {
+		let mut links = vec![];
+		let mut current = index.clone();
+		let last = loop {
+			let Node::If { condition, then_branch, else_branch, .. } = self.ast.get(&current) else {
+				unreachable!("if_chain on a node that isn't an if");
+			};
+			links.push((current.clone(), condition, then_branch));
+			match else_branch {
+				Some(next) if matches!(self.ast.get(next), Node::If { .. }) => current = next.clone(),
+				_ => break else_branch,
+			}
+		};
+
+		let statement = !value && expected.is_none();
+		if last.is_none() && !statement {
+			// reported at the `if` the `else` is missing from
+			let (link, ..) = links.last().unwrap();
+			self.report.error_at(self.ast.span(link), "'if' used as a value must have an 'else' branch".to_string());
+		}
+
+		let mut ty = expected;
+		for (_, condition, then_branch) in &links {
+			let condition_ty = self.bindings.add(Type::Bool);
+			self.impost(condition, condition_ty);
+			match ty {
+				_ if statement && last.is_none() => {
+					self.annotate(then_branch, false);
+				}
+				Some(ty) => {
+					self.impost(then_branch, ty);
+				}
+				None => ty = Some(self.annotate(then_branch, value)),
+			}
+		}
+		if let Some(last) = last {
+			let ty = ty.unwrap();
+			self.impost(last, ty);
+		}
+
+		let ty = ty.unwrap_or_else(|| self.bindings.add(Type::Unit));
+		for (link, ..) in &links[1..] {
+			self.types.insert(link, ty);
+		}
+		ty
+	}
+
+	fn else_branch(&mut self, index: &NodeIndex, then_ty: TypeIndex, else_branch: &Option<NodeIndex>, value: bool) -> TypeIndex {
+		match else_branch {
+			Some(else_branch) => {
+				self.impost(else_branch, then_ty);
+				then_ty
+			}
+			None if value => {
+				self.report.error_at(self.ast.span(index), "'if' used as a value must have an 'else' branch".to_string());
+				then_ty
+			}
+			None => self.bindings.add(Type::Unit),
+		}
+	}
+
+	fn statements(&mut self, expr: &[NodeIndex], value: bool) -> TypeIndex {
+		// named functions can be called before they're declared, so their
+		// names are bound up front. the node keeps the name's type for when
+		// the function itself is reached
+		for v in expr {
+			if let Node::Fn { name: Some(name), .. } = self.ast.get(v) {
+				let ty = self.bindings.fresh();
+				self.bindings.define(name.get(self.src), ty);
+				self.types.insert(v, ty);
+			}
+		}
+
+		let mut ty = None;
+		for (i, v) in expr.iter().enumerate() {
+			// only the tail of a block inherits the block's position
+			ty = Some(self.annotate(v, value && i == expr.len() - 1));
+		}
+		match ty {
+			Some(ty) => ty,
+			None => self.bindings.add(Type::Unit),
+		}
+	}
+
+	fn bind_pattern(&mut self, pattern: &'a Pattern<'a>, ty: TypeIndex, mutable: bool) {
+		match pattern {
+			Pattern::Identifier { name } => {
+				// a mutable binding can be reassigned, so it keeps one type
+				if mutable {
+					self.bindings.define(name.get(self.src), ty);
+				} else {
+					self.bindings.generalize(name.get(self.src), ty);
+				}
+			}
+			Pattern::Tuple { op, items } => {
+				let arity = match self.bindings.get(self.bindings.find(ty)) {
+					Type::Tuple(found) => Some(found.len()),
+					_ => None,
+				};
+				let elements = items.iter().map(|_| self.bindings.fresh()).collect::<Vec<_>>();
+				match arity {
+					Some(found) if found != items.len() => {
+						self.report.error_at(
+							op.span(),
+							format!("expected a tuple of {} elements, found {}", items.len(), found),
+						);
+					}
+					_ => {
+						let expected = self.bindings.add(Type::Tuple(elements.clone()));
+						self.expect_at(op.span(), ty, expected);
+					}
+				}
+				for (item, ty) in items.iter().zip(elements) {
+					self.bind_pattern(item, ty, mutable);
+				}
+			}
+		}
+	}
+
+	fn annotate_type(&mut self, index: &NodeIndex) -> TypeIndex {
+		let ty = match self.ast.get(index) {
+			Node::Identifier { name: token } => {
+				let name = token.get(self.src);
+				match builtin_type(name) {
+					Some(ty) => ty,
+					None => match self.bindings.aliases.last().unwrap().get(name) {
+						Some(Some(ty)) => return *ty,
+						Some(None) => {
+							self.report.error_at(token.span(), format!("type alias '{}' refers to itself", name));
+							return self.bindings.fresh();
+						}
+						None if self.bindings.scheme(name).is_some() => {
+							self.report.error_at(token.span(), format!("expected a type, found value '{}'", name));
+							return self.bindings.fresh();
+						}
+						None => {
+							self.report.error_at(token.span(), format!("unknown type '{}'", name));
+							return self.bindings.fresh();
+						}
+					},
+				}
+			}
+			Node::Optional { ty, .. } => Type::Option(self.annotate_type(ty)),
+			Node::ArrayType { ty } => Type::Array(self.annotate_type(ty)),
+			_ => return self.bindings.fresh(),
+		};
+		self.bindings.add(ty)
 	}
 
-	fn annotate(&mut self, index: NodeIndex) {
-		let node = self.ast.get(&index);
-		
+	/// infers the type of a node. `value` is whether the node's result is
+	/// used by its parent, as opposed to sitting in statement position
+	fn annotate(&mut self, index: &NodeIndex, value: bool) -> TypeIndex {
+		let ty = match self.ast.get(index) {
+			Node::Error => self.bindings.fresh(),
+			// only parsed in type position, which goes through `annotate_type`
+			Node::Optional { .. } | Node::ArrayType { .. } => self.bindings.fresh(),
+			// bounds aren't checked until there are generics to check them on
+			Node::Where { .. } => self.bindings.fresh(),
+
+			// the module's bindings go in the outermost scope rather than one
+			// of their own, so they can still be looked up after the walk
+			Node::Module { root } => match self.ast.get(root) {
+				Node::Block { expr } => {
+					let ty = self.statements(expr, false);
+					self.types.insert(root, ty);
+					ty
+				}
+				_ => self.annotate(root, false),
+			},
+
+			Node::Block { expr } => {
+				self.bindings.scope_begin();
+				let ty = self.statements(expr, value);
+				self.bindings.scope_end();
+				ty
+			}
+
+			Node::Identifier { name: token } => {
+				let name = token.get(self.src);
+				match self.bindings.lookup(name) {
+					Some(ty) => ty,
+					// types and values are separate namespaces, so a type's name
+					// is never a value
+					None if builtin_type(name).is_some() || self.bindings.aliases.last().unwrap().contains_key(name) => {
+						self.report.error_at(token.span(), format!("expected a value, found type '{}'", name));
+						self.bindings.fresh()
+					}
+					None => {
+						self.report.error_at(token.span(), format!("undefined identifier '{}'", name));
+						self.bindings.fresh()
+					}
+				}
+			}
+
+			Node::Bool { .. } => self.bindings.add(Type::Bool),
+			Node::Nil => {
+				let inner = self.bindings.fresh();
+				self.bindings.add(Type::Option(inner))
+			}
+			Node::Integer { value } => {
+				let ty = self.suffix(value).unwrap_or_else(|| self.options.default_int.clone());
+				self.bindings.add(ty)
+			}
+			Node::Float { value } => {
+				self.suffix(value);
+				self.float(value);
+				self.bindings.add(Type::Float)
+			}
+
+			Node::Fn { name, args, ret, expr, .. } => {
+				// a named function is in scope in its own body
+				let own = name.map(|name| {
+					let ty = match self.types.get(index) {
+						Some(ty) => *ty,
+						None => self.bindings.fresh(),
+					};
+					self.bindings.define(name.get(self.src), ty);
+					(name.get(self.src), ty)
+				});
+
+				self.bindings.scope_begin();
+				self.functions += 1;
+				// loops outside the function can't be broken out of from inside it
+				let loops = std::mem::take(&mut self.loops);
+
+				let mut items = Vec::new();
+				for (name, annotation) in args {
+					let ty = match annotation {
+						Some(annotation) => self.annotate_type(annotation),
+						None => self.bindings.fresh(),
+					};
+					self.bindings.define(name.get(self.src), ty);
+					items.push(ty);
+				}
+
+				let body = match ret {
+					Some(ret) => {
+						let ret = self.annotate_type(ret);
+						self.impost(expr, ret)
+					}
+					None => self.annotate(expr, true),
+				};
+
+				self.loops = loops;
+				self.functions -= 1;
+				self.bindings.scope_end();
+
+				let args = self.bindings.add(Type::Tuple(items));
+				let ty = self.bindings.add(Type::Fn(args, body));
+
+				if let Some((name, own)) = own {
+					self.expect(index, ty, own);
+					// without its own monomorphic binding in the way, the
+					// function can be generalized like a `let`
+					self.bindings.map.last_mut().unwrap().remove(name);
+					self.bindings.generalize(name, ty);
+				}
+				ty
+			}
+
+			Node::Group { expr } => self.annotate(expr, true),
+
+			Node::Record { fields } => {
+				let mut items: Vec<(String, TypeIndex)> = Vec::new();
+				for (token, value) in fields {
+					let ty = self.annotate(value, true);
+					let name = token.get(self.src);
+					match items.binary_search_by(|(v, _)| v.as_str().cmp(name)) {
+						Ok(_) => self.report.error_at(token.span(), format!("duplicate field '{}'", name)),
+						Err(at) => items.insert(at, (name.to_string(), ty)),
+					}
+				}
+				self.bindings.add(Type::Record(items))
+			}
+
+			Node::Tuple { items } => {
+				let items = items.iter().map(|v| self.annotate(v, true)).collect();
+				self.bindings.add(Type::Tuple(items))
+			}
+
+			// every element must have the first one's type. an empty array's
+			// element type is left for later uses to decide
+			Node::Array { items } => {
+				let elem = self.bindings.fresh();
+				for v in items {
+					self.impost(v, elem);
+				}
+				self.bindings.add(Type::Array(elem))
+			}
+
+			Node::Ascribe { expr, ty } => {
+				let ty = self.annotate_type(ty);
+				self.impost(expr, ty);
+				ty
+			}
+
+			Node::Binary { left, op, right } => {
+				if matches!(op.kind, TT::Slash | TT::Percent) && self.is_zero(right) {
+					self.report.error_at(op.span(), "division by zero".to_string());
+				}
+
+				self.binary(op, left, right)
+			}
+
+			Node::Unary { op, right } => {
+				if op.kind == TT::Bang {
+					let expected = self.bindings.add(Type::Bool);
+					self.impost(right, expected)
+				} else {
+					self.annotate(right, true)
+				}
+			}
+
+			Node::Call { op, expr, args } => {
+				let callee = self.annotate(expr, true);
+
+				// when the callee is already known to be a function, its
+				// parameter types become expectations for the arguments
+				let known = match self.bindings.get(self.bindings.find(callee)) {
+					Type::Fn(params, ret) => match self.bindings.get(self.bindings.find(*params)) {
+						Type::Tuple(params) => Some((params.clone(), *ret)),
+						_ => None,
+					},
+					_ => None,
+				};
+				let params = match known {
+					Some((params, ret)) if params.len() != args.len() => {
+						let callee = match self.ast.get(expr) {
+							Node::Identifier { name } => format!("function '{}'", name.get(self.src)),
+							_ => "function".to_string(),
+						};
+						self.report.error_at(op.span(), format!(
+							"{} expects {} argument{}, found {}",
+							callee, params.len(), if params.len() == 1 { "" } else { "s" }, args.len(),
+						));
+						for v in args {
+							self.annotate(v, true);
+						}
+						self.types.insert(index, ret);
+						return ret;
+					}
+					Some((params, _)) => Some(params),
+					None => None,
+				};
+				let items = match params {
+					Some(params) => {
+						for (v, p) in args.iter().zip(params.iter()) {
+							self.impost(v, *p);
+						}
+						params
+					}
+					None => args.iter().map(|v| self.annotate(v, true)).collect(),
+				};
+				let args = self.bindings.add(Type::Tuple(items));
+				let ret = self.bindings.fresh();
+				let expected = self.bindings.add(Type::Fn(args, ret));
+				self.expect(expr, callee, expected);
+				ret
+			}
+
+			// indexing takes an array and an integer, and gives an element
+			Node::Index { expr, index, .. } => {
+				let elem = self.bindings.fresh();
+				let array = self.bindings.add(Type::Array(elem));
+				self.impost(expr, array);
+				let expected = self.bindings.add(Type::Int);
+				self.impost(index, expected);
+				elem
+			}
+			// a record's field by name, or a tuple's item by position. the
+			// type has to be known by now, since there's no way to infer a
+			// record from one of its fields
+			Node::Field { expr, name, .. } => {
+				let ty = self.annotate(expr, true);
+				let field = name.get(self.src);
+				let found = match self.bindings.shallow(ty) {
+					Type::Record(fields) => fields.iter().find(|(v, _)| v == field).map(|(_, v)| *v),
+					Type::Tuple(items) => field.parse::<usize>().ok().and_then(|v| items.get(v).copied()),
+					Type::Var(_) => {
+						self.report.error_at(name.span(), format!("the type must be known here to access its field '{}'", field));
+						return self.bindings.fresh();
+					}
+					_ => None,
+				};
+				match found {
+					Some(ty) => ty,
+					None => {
+						self.report.error_at(name.span(), format!("no field '{}' on type '{}'", field, self.bindings.display(ty)));
+						self.bindings.fresh()
+					}
+				}
+			}
+			Node::Slice { expr, range, .. } => {
+				let ty = self.annotate(expr, true);
+				self.annotate(range, true);
+				ty
+			}
+			Node::Range { start, end, .. } => {
+				for bound in start.iter().chain(end.iter()) {
+					let expected = self.bindings.add(Type::Int);
+					self.impost(bound, expected);
+				}
+				self.bindings.fresh()
+			}
+
+			// error propagation has nothing to propagate through yet, so this
+			// is only checked for placement
+			Node::Try { expr, op } => {
+				if self.functions == 0 {
+					self.report.error_at(op.span(), "'?' can only be used inside a function body".to_string());
+				}
+				self.annotate(expr, true)
+			}
+
+			Node::If { .. } => self.if_chain(index, value, None),
+
+			// the pattern's bindings are only visible in the then branch
+			Node::IfLet { pattern, expr, then_branch, else_branch, .. } => {
+				let ty = self.annotate(expr, true);
+				self.bindings.scope_begin();
+				self.bind_pattern(pattern, ty, false);
+				let then_ty = self.annotate(then_branch, value);
+				self.bindings.scope_end();
+				self.else_branch(index, then_ty, else_branch, value)
+			}
+
+			// a `loop` is whatever its `break`s leave it with, or Unit if
+			// nothing breaks out of it
+			Node::Loop { label, body, .. } => {
+				let ty = self.bindings.fresh();
+				self.loop_body(*label, Some(ty), body);
+				ty
+			}
+
+			Node::While { label, condition, body, .. } => {
+				let expected = self.bindings.add(Type::Bool);
+				self.impost(condition, expected);
+				self.loop_body(*label, None, body);
+				self.bindings.add(Type::Unit)
+			}
+
+			// the condition is outside the body's scope, so it can't see
+			// bindings made in the body
+			Node::RepeatWhile { label, body, condition, .. } => {
+				self.loop_body(*label, None, body);
+				let expected = self.bindings.add(Type::Bool);
+				self.impost(condition, expected);
+				self.bindings.add(Type::Unit)
+			}
+
+			// control never continues past a jump, so it fits anywhere
+			Node::Break { op, label, .. } | Node::Continue { op, label } => {
+				let keyword = op.get(self.src);
+				let target = match label {
+					Some(label) => self.loops.iter().rposition(|v| v.label == Some(label.get(self.src))),
+					None => self.loops.len().checked_sub(1),
+				};
+				match (label, target) {
+					(_, _) if self.loops.is_empty() => {
+						self.report.error_at(op.span(), format!("'{}' outside of a loop", keyword));
+					}
+					(Some(label), None) => {
+						self.report.error_at(label.span(), format!("undefined label {}", label.get(self.src)));
+					}
+					_ => (),
+				}
+
+				if let Node::Break { value, .. } = self.ast.get(index) {
+					self.break_value(op, target, value.as_ref());
+				}
+				self.bindings.fresh()
+			}
+
+			Node::Export { item, .. } => self.annotate(item, value),
+			// attributes don't mean anything to the checker yet
+			Node::Attribute { item, .. } => self.annotate(item, value),
+
+			// the alias is in scope while its own type is resolved, so that
+			// `type A = A` is caught rather than reported as unknown
+			Node::TypeAlias { name, ty, .. } => {
+				let name = name.get(self.src);
+				self.bindings.aliases.last_mut().unwrap().insert(name, None);
+				let ty = self.annotate_type(ty);
+				self.bindings.aliases.last_mut().unwrap().insert(name, Some(ty));
+				self.bindings.add(Type::Unit)
+			}
+
+			// each variant is a constructor, a function from its payload to
+			// the enum, or just a value of the enum if it carries nothing. the
+			// name is in scope first, so a payload can refer to its own enum
+			Node::Enum { name, variants, .. } => {
+				let name = name.get(self.src);
+				let ty = self.bindings.add(Type::Enum(name.to_string()));
+				self.bindings.aliases.last_mut().unwrap().insert(name, Some(ty));
+				for (variant, payload) in variants {
+					let constructor = if payload.is_empty() {
+						ty
+					} else {
+						let items = payload.iter().map(|v| self.annotate_type(v)).collect();
+						let args = self.bindings.add(Type::Tuple(items));
+						self.bindings.add(Type::Fn(args, ty))
+					};
+					self.bindings.define(variant.get(self.src), constructor);
+				}
+				self.bindings.add(Type::Unit)
+			}
+
+			// an assignment evaluates to the value assigned, so chains like
+			// `a = b = 1` check each target against the same value
+			Node::Assign { target, value, .. } => {
+				if let Node::Identifier { name } = self.ast.get(target)
+					&& self.bindings.consts.last().unwrap().contains_key(name.get(self.src))
+				{
+					self.report.error_at(name.span(), format!("cannot assign to constant '{}'", name.get(self.src)));
+				}
+				let target = self.annotate(target, true);
+				self.impost(value, target)
+			}
+
+			// the initializer is checked like any other, then run, so that
+			// later constants can use its value
+			Node::Const { name, ty, expr, .. } => {
+				let errors = self.report.errors().len();
+				let ty = match ty {
+					Some(ty) => {
+						let expected = self.annotate_type(ty);
+						self.impost(expr, expected)
+					}
+					None => self.annotate(expr, true),
+				};
+				let name = name.get(self.src);
+				self.bindings.generalize(name, ty);
+
+				if self.report.errors().len() == errors {
+					match eval::eval_const(self.ast, expr, self.bindings.consts.last().unwrap()) {
+						Ok(value) => {
+							self.bindings.consts.last_mut().unwrap().insert(name, value);
+						}
+						Err(report) => {
+							for v in report.errors() {
+								match v.span {
+									Some(span) => self.report.error_at(span, v.message.clone()),
+									None => self.report.error(v.message.clone()),
+								}
+							}
+						}
+					}
+				}
+				self.bindings.add(Type::Unit)
+			}
+
+			Node::Let { mutable, pattern, expr, annotation } => {
+				let ty = match annotation {
+					Some(annotation) => {
+						let expected = self.annotate_type(annotation);
+						self.impost(expr, expected)
+					}
+					None => self.annotate(expr, true),
+				};
+				self.bind_pattern(pattern, ty, *mutable);
+				self.bindings.add(Type::Unit)
+			}
+		};
+
+		self.types.insert(index, ty);
+		ty
 	}
 }
 
-fn annotate<'a>(ast: Ast<'a>) -> Tst<'a> {
-	let mut count = 0;
+/// the binding and type checking pass over an [`Ast`]
+#[derive(Debug)]
+pub struct Check<'a> {
+	annotate: Annotate<'a>,
+}
+impl<'a> Check<'a> {
+	pub fn new(src: &'a str, ast: &'a Ast<'a>) -> Self {
+		Self::with_options(src, ast, BindOptions::default())
+	}
+
+	pub fn with_options(src: &'a str, ast: &'a Ast<'a>, options: BindOptions) -> Self {
+		Self {
+			annotate: Annotate::new(src, ast, options),
+		}
+	}
+
+	pub fn walk(&mut self, index: &NodeIndex) {
+		self.annotate.annotate(index, false);
+		self.annotate.literals();
+	}
+
+	pub fn report(&self) -> &Report {
+		&self.annotate.report
+	}
+
+	/// runs the pass over the whole tree, producing a [`Tst`] if it type checks.
+	/// a [`Check::walk`] from the root is reused rather than run again
+	pub fn build(self) -> Result<Tst<'a>, Report> {
+		let (tst, report) = self.annotate.build();
+		report.into_result(tst)
+	}
+
+	/// the inferred type of a binding in the outermost scope, with any
+	/// quantified variables written out, such as `forall a. (a) -> a`
+	pub fn signature(&self, name: &str) -> Option<String> {
+		let bindings = &self.annotate.bindings;
+		bindings.map.first()?.get(name)?.as_ref().map(|v| bindings.display_scheme(v))
+	}
 
-	let mut types = Vec::with_capacity(ast.nodes.len());
-	for _ in 0..types.len() {
-		types.push(Type::Var(count));
-		count += 1;
+	/// the inferred type of a binding in the outermost scope
+	pub fn binding_type(&self, name: &str) -> Option<ResolvedType> {
+		let bindings = &self.annotate.bindings;
+		bindings.map.first()?.get(name)?.as_ref().map(|v| bindings.resolve(v.ty))
 	}
 
-	Tst {
-		tokens: ast.tokens,
-		nodes: ast.nodes,
-		root: ast.root,
-		types
+	/// the span of the name that declared a binding in the outermost scope.
+	/// if the name was declared more than once, the last declaration is the
+	/// one in scope
+	pub fn binding_span(&self, name: &str) -> Option<Span> {
+		self.annotate.bindings.map.first()?.get(name)?;
+
+		let ast = self.annotate.ast;
+		let src = self.annotate.src;
+		let Node::Module { root } = ast.get(&ast.root) else {
+			return None;
+		};
+		let statements = match ast.get(root) {
+			Node::Block { expr } => expr.clone(),
+			_ => vec![root.clone()],
+		};
+		statements.iter().rev().find_map(|v| {
+			let mut node = ast.get(v);
+			while let Node::Export { item, .. } | Node::Attribute { item, .. } = node {
+				node = ast.get(item);
+			}
+			let tokens = match node {
+				Node::Let { pattern, .. } => pattern.names(),
+				Node::Fn { name: Some(name), .. }
+				| Node::Const { name, .. } => vec![*name],
+				_ => vec![],
+			};
+			tokens.into_iter()
+				.find(|token| token.get(src) == name)
+				.map(|token| token.span())
+		})
 	}
 }
 
 
+// whether `value` is exactly the decimal written as `text`. every f64 has a
+// finite decimal expansion, 1074 fractional digits at most, so the two can be
+// compared as strings once trailing zeros are gone
+fn exact_float(text: &str, value: f64) -> bool {
+	let trim = |v: &str| -> String {
+		let (int, frac) = v.split_once('.').unwrap_or((v, ""));
+		let int = match int.trim_start_matches('0') {
+			"" => "0",
+			int => int,
+		};
+		match frac.trim_end_matches('0') {
+			"" => int.to_string(),
+			frac => format!("{}.{}", int, frac),
+		}
+	};
+	trim(text) == trim(&format!("{:.1074}", value))
+}
+
 #[cfg(test)]
 mod test {
-    use crate::{bind::Check, parse, resolve, tokenize};
+    use crate::{bind::{BindOptions, Check, Operator, ResolvedType, Type}, parse, parse::Node, token::{Span, SUFFIXES, TT}, tokenize};
 
 	#[test]
 	fn run() {
@@ -123,7 +1567,958 @@ mod test {
 		bind.walk(&ast.root);
 		println!("{:#?}", bind);
 
-		panic!("complete :3")
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert!(bind.report().is_empty());
+		assert_eq!(bind.signature("x").unwrap(), "Int");
+	}
+
+	#[test]
+	fn if_value_with_else() {
+		let src = "let c = true; let x = if c 1 else 2";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok());
 	}
-}
 
+	#[test]
+	fn if_value_without_else() {
+		let src = "let c = true; let x = if c 1";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(!bind.report().ok());
+	}
+
+	#[test]
+	fn impost_condition() {
+		let src = "if 1 2 else 3";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert_eq!(bind.report().errors()[0].message, "type mismatch: expected 'Bool', found 'Int'");
+		assert_eq!(bind.report().errors()[0].span, Some(Span::new(3, 4)));
+
+		let src = "let x: Int = true";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert_eq!(bind.report().errors()[0].message, "type mismatch: expected 'Int', found 'Bool'");
+		assert_eq!(bind.report().errors()[0].span, Some(Span::new(13, 17)));
+	}
+
+	#[test]
+	fn impost_arguments() {
+		let src = "let f = fn (a: Bool) => a; f(1); f(true)";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert_eq!(bind.report().errors().len(), 1);
+		assert_eq!(bind.report().errors()[0].message, "type mismatch: expected 'Bool', found 'Int'");
+		assert_eq!(bind.report().errors()[0].span, Some(Span::new(29, 30)));
+	}
+
+	#[test]
+	fn division_by_zero() {
+		for src in ["1 / 0", "let x = 1; x % 0", "1.5 / (0.0)"] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let mut bind = Check::new(src, &ast);
+			bind.walk(&ast.root);
+			assert_eq!(bind.report().errors()[0].message, "division by zero", "{}", src);
+		}
+
+		let src = "1 / 0";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert_eq!(bind.report().errors()[0].span, Some(Span::new(2, 3)));
+	}
+
+	#[test]
+	fn division_by_variable() {
+		let src = "let x = 0; 1 / x; 10 % 3";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok());
+	}
+
+	#[test]
+	fn ascribe() {
+		let src = "let x = (0: Float); let y: Float = x";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok());
+
+		let src = "(true: Int)";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert_eq!(bind.report().errors()[0].message, "type mismatch: expected 'Int', found 'Bool'");
+	}
+
+	#[test]
+	fn literal_range() {
+		for (src, ok) in [
+			("let x: i8 = 127", true),
+			("let x: i8 = 128", false),
+			("let x: u8 = 255", true),
+			("let x: u8 = 300", false),
+			("let x = 300; let y: u8 = x", false),
+			("let x: u64 = 18446744073709551615", true),
+			("9223372036854775807", true),
+			("9223372036854775808", false),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let mut bind = Check::new(src, &ast);
+			bind.walk(&ast.root);
+			assert_eq!(bind.report().ok(), ok, "{}", src);
+		}
+
+		let src = "let x: i8 = 300";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert_eq!(bind.report().errors()[0].message, "integer literal out of range for 'i8'");
+		assert_eq!(bind.report().errors()[0].span, Some(Span::new(12, 15)));
+	}
+
+	#[test]
+	fn record() {
+		let src = "let a = { x = 1, y = true }; let b = { y = false, x = 2 }; if true a else b";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok());
+
+		let src = "let a = { x = 1, }; let b = { x = true, }; if true a else b";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert_eq!(bind.report().errors()[0].message, "type mismatch: expected '{ x: Int }', found '{ x: Bool }'");
+
+		let src = "{ x = 1, x = 2 }";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert_eq!(bind.report().errors()[0].message, "duplicate field 'x'");
+		assert_eq!(bind.report().errors()[0].span, Some(Span::new(9, 10)));
+	}
+
+	#[test]
+	fn labels() {
+		let src = "'outer: loop { let c = true; while c { break 'outer } }";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok());
+
+		let src = "'outer: loop { loop { continue 'inner } }";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert_eq!(bind.report().errors()[0].message, "undefined label 'inner");
+
+		let src = "loop { fn () => break }";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert_eq!(bind.report().errors()[0].message, "'break' outside of a loop");
+	}
+
+	#[test]
+	fn try_placement() {
+		let src = "let f = fn () => 1; let g = fn () => f()?";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok());
+
+		let src = "let f = fn () => 1; f()?";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert_eq!(bind.report().errors()[0].message, "'?' can only be used inside a function body");
+	}
+
+	#[test]
+	fn default_int() {
+		for (src, default_int, ok) in [
+			("let x = 0; let y: Int = x", Type::Int, true),
+			("let x = 0; let y: Float = x", Type::Int, false),
+			("let x = 0; let y: Int = x", Type::Float, false),
+			("let x = 0; let y: Float = x", Type::Float, true),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let mut bind = Check::with_options(src, &ast, BindOptions { default_int, ..Default::default() });
+			bind.walk(&ast.root);
+			assert_eq!(bind.report().ok(), ok, "{}", src);
+		}
+	}
+
+	#[test]
+	fn if_statement_without_else() {
+		let src = "let c = true; if c { }";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok());
+	}
+
+	#[test]
+	fn generalize() {
+		let src = "let id = fn (a) => a; let x = id(1); let y = id(true); mut z = id";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert_eq!(bind.signature("id").unwrap(), "forall a. (a) -> a");
+		assert_eq!(bind.signature("x").unwrap(), "Int");
+		assert!(!bind.signature("z").unwrap().starts_with("forall"));
+	}
+
+	#[test]
+	fn generalize_pair() {
+		let src = "let k = fn (a, b) => a";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert_eq!(bind.signature("k").unwrap(), "forall a b. (a, b) -> a");
+	}
+
+	#[test]
+	fn tuple_pattern() {
+		let src = "let (a, b) = (1, true)";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert_eq!(bind.signature("a").unwrap(), "Int");
+		assert_eq!(bind.signature("b").unwrap(), "Bool");
+	}
+
+	#[test]
+	fn tuple_pattern_arity() {
+		let src = "let (a, b) = (1,)";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert_eq!(bind.report().errors()[0].message, "expected a tuple of 2 elements, found 1");
+		assert_eq!(bind.report().errors()[0].span, Some(Span::new(4, 5)));
+	}
+
+	#[test]
+	fn assign_chain() {
+		let src = "mut a = 0.5; mut b = 0.5; let c = a = b = 1.5";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert_eq!(bind.signature("c").unwrap(), "Float");
+
+		let src = "mut a = true; mut b = 0; a = b = 1";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert_eq!(bind.report().errors()[0].message, "type mismatch: expected 'Bool', found 'Int'");
+	}
+
+	#[test]
+	fn block_scope() {
+		let src = "{ let x = 1 }; x";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert_eq!(bind.report().errors()[0].message, "undefined identifier 'x'");
+		assert_eq!(bind.report().errors()[0].span, Some(Span::new(15, 16)));
+		assert!(bind.signature("x").is_none());
+
+		let src = "let x = 1; let y = { let z = x; z }";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert_eq!(bind.signature("y").unwrap(), "Int");
+		assert!(bind.signature("z").is_none());
+	}
+
+	#[test]
+	fn operators() {
+		let src = "let x = 1; let y = x + 2";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert_eq!(bind.signature("y").unwrap(), "Int");
+
+		let src = "let y = true + 2";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert_eq!(bind.report().errors()[0].message, "no operator '+' for 'Bool' and 'Int'");
+		assert_eq!(bind.report().errors()[0].span, Some(Span::new(13, 14)));
+
+		let options = BindOptions {
+			operators: vec![Operator { op: TT::Plus, left: Type::Bool, right: Type::Int, result: Type::Int }],
+			..Default::default()
+		};
+		let mut bind = Check::with_options(src, &ast, options);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert_eq!(bind.signature("y").unwrap(), "Int");
+
+		// a number on the left is checked against both operands too
+		for (src, message) in [
+			("1 + true", "no operator '+' for 'Int' and 'Bool'"),
+			("let x: u8 = 1; x * 2i32", "no operator '*' for 'u8' and 'i32'"),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let mut bind = Check::new(src, &ast);
+			bind.walk(&ast.root);
+			assert_eq!(bind.report().errors()[0].message, message, "{}", src);
+		}
+		let src = "let x: u8 = 1; let y = x + 2; let z = (1.5 < x)";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert_eq!(bind.report().errors()[0].message, "no operator '<' for 'Float' and 'u8'");
+		assert_eq!(bind.signature("y").unwrap(), "u8");
+	}
+
+	#[test]
+	fn if_let() {
+		let src = "let foo = 1; let y = if let x = foo { x } else { 0 }; x";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert_eq!(bind.signature("y").unwrap(), "Int");
+		assert_eq!(bind.report().errors().len(), 1);
+		assert_eq!(bind.report().errors()[0].message, "undefined identifier 'x'");
+	}
+
+	#[test]
+	fn build() {
+		let src = "let x = 0";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let tst = Check::new(src, &ast).build().unwrap();
+		let Node::Module { root } = tst.get(&tst.root) else { panic!() };
+		let Node::Block { expr } = tst.get(root) else { panic!() };
+		let Node::Let { expr, .. } = tst.get(&expr[0]) else { panic!() };
+		assert_eq!(tst.ty(expr), Some(&Type::Int));
+
+		// building after a walk reuses it
+		let src = "let x = undefinedthing";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		let report = bind.build().unwrap_err();
+		let messages = report.errors().iter().map(|v| v.message.as_str()).collect::<Vec<_>>();
+		assert_eq!(messages, vec!["undefined identifier 'undefinedthing'"]);
+
+		let src = "let f = fn (a: Int) => a";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let tst = Check::new(src, &ast).build().unwrap();
+		let Node::Module { root } = tst.get(&tst.root) else { panic!() };
+		let Node::Block { expr } = tst.get(root) else { panic!() };
+		let Node::Let { expr, .. } = tst.get(&expr[0]) else { panic!() };
+		assert!(matches!(tst.ty(expr), Some(Type::Fn(..))));
+		assert_eq!(tst.display(expr).unwrap(), "(Int) -> Int");
+
+		let src = "let x: Bool = 0";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		assert!(Check::new(src, &ast).build().is_err());
+	}
+
+	#[test]
+	fn nil() {
+		let src = "let x: Int? = nil; let y = nil; let z: Bool? = y";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert_eq!(bind.signature("x").unwrap(), "Int?");
+
+		let src = "let x: Int = nil";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().errors()[0].message.starts_with("type mismatch: expected 'Int', found '"));
+	}
+
+	#[test]
+	fn arity() {
+		for (src, message) in [
+			("let f = fn (a, b) => a; f(1)", "function 'f' expects 2 arguments, found 1"),
+			("let f = fn (a) => a; f(1, 2)", "function 'f' expects 1 argument, found 2"),
+			("(fn () => 0)(1)", "function expects 0 arguments, found 1"),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let mut bind = Check::new(src, &ast);
+			bind.walk(&ast.root);
+			assert_eq!(bind.report().errors().len(), 1, "{}", bind.report());
+			assert_eq!(bind.report().errors()[0].message, message);
+		}
+	}
+
+	#[test]
+	fn repeat_while() {
+		let src = "mut x = 0; repeat x = x + 1 while x < 10; repeat { break } while x";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert_eq!(bind.report().errors().len(), 1, "{}", bind.report());
+		assert_eq!(bind.report().errors()[0].message, "type mismatch: expected 'Bool', found 'Int'");
+	}
+
+	#[test]
+	fn type_alias() {
+		let src = "type Id = Int; let x: Id = 1; x";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let tst = Check::new(src, &ast).build().unwrap();
+		let Node::Module { root } = tst.get(&tst.root) else { panic!() };
+		let Node::Block { expr } = tst.get(root) else { panic!() };
+		assert_eq!(tst.ty(&expr[2]), Some(&Type::Int));
+
+		for (src, message) in [
+			("type Id = Int; let x: Id = true", "type mismatch: expected 'Int', found 'Bool'"),
+			("type A = A", "type alias 'A' refers to itself"),
+			("type A = A?", "type alias 'A' refers to itself"),
+			("{ type Id = Int }; let x: Id = 1", "unknown type 'Id'"),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let report = Check::new(src, &ast).build().unwrap_err();
+			assert_eq!(report.errors()[0].message, message, "{}", src);
+		}
+
+		let src = "{ type Id = Int }; let x: Id = 1";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let report = Check::new(src, &ast).build().unwrap_err();
+		assert_eq!(report.errors()[0].span, Some(Span::new(26, 28)));
+	}
+
+	#[test]
+	fn annotated() {
+		for (src, annotated) in [
+			("let x = 1", "let x: Int = 1"),
+			("let x: Int = 1; mut y = x > 0", "let x: Int = 1; mut y: Bool = x > 0"),
+			("let f = fn (a, b: Float) => a + b", "let f = fn (a: Float, b: Float): Float => a + b"),
+			("fn (a: Int?) where T: U => a", "fn (a: Int?): Int? where T: U => a"),
+			("let id = fn (a) => a", "let id = fn (a) => a"),
+			("let (a, b) = (1, 2)", "let (a, b) = (1, 2)"),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let tst = Check::new(src, &ast).build().unwrap();
+			assert_eq!(tst.annotated(), annotated, "{}", src);
+		}
+	}
+
+	#[test]
+	fn constant() {
+		let src = "const PI = 3.14; const TAU: Float = PI * 2.0; TAU";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert_eq!(bind.signature("TAU").unwrap(), "Float");
+
+		for (src, message) in [
+			("let someFn = fn () => 1; const X = someFn()", "'someFn' is not a constant"),
+			("let x = 1; const X = x + 1", "'x' is not a constant"),
+			("const X = 1; let X = 2; const Y = X", "'X' is not a constant"),
+			("const X = 1 / 0", "division by zero"),
+			("const X = 1; X = 2", "cannot assign to constant 'X'"),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let report = Check::new(src, &ast).build().unwrap_err();
+			assert_eq!(report.errors()[0].message, message, "{}", src);
+		}
+	}
+
+	#[test]
+	fn named_fn() {
+		let src = "{ fn fact (n) => if n < 2 1 else n * fact(n - 1); fact(5) }";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let tst = Check::new(src, &ast).build().unwrap();
+		let Node::Module { root } = tst.get(&tst.root) else { panic!() };
+		let Node::Block { expr } = tst.get(root) else { panic!() };
+		assert_eq!(tst.ty(&expr[0]), Some(&Type::Int));
+
+		let src = "let a = even(4); fn even (n) => if n == 0 true else odd(n - 1); fn odd (n) => if n == 0 false else even(n - 1); fn id (x) => x";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert_eq!(bind.signature("a").unwrap(), "Bool");
+		assert_eq!(bind.signature("odd").unwrap(), "(Int) -> Bool");
+		assert_eq!(bind.signature("id").unwrap(), "forall a. (a) -> a");
+
+		let src = "let f = fn (a) => a + 1; f(2)";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		assert!(Check::new(src, &ast).build().is_ok());
+
+		let src = "{ fn f () => 1 }; f()";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let report = Check::new(src, &ast).build().unwrap_err();
+		assert_eq!(report.errors()[0].message, "undefined identifier 'f'");
+	}
+
+	#[test]
+	fn float_precision() {
+		let large = format!("1{}.0", "0".repeat(400));
+		let tokens = tokenize(&large).unwrap();
+		let ast = parse(&large, &tokens).unwrap();
+		let report = Check::new(&large, &ast).build().unwrap_err();
+		assert_eq!(report.errors()[0].message, format!("float literal '{}' is too large", large));
+
+		let src = "let a = 0.1; let b = 0.5 + 2.0 + 007.250; let c = 0.3";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().is_empty(), "{}", bind.report());
+
+		let options = BindOptions { warn_float_precision: true, ..Default::default() };
+		let mut bind = Check::with_options(src, &ast, options);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok());
+		let warnings = bind.report().warnings();
+		assert_eq!(warnings.len(), 2);
+		assert_eq!(warnings[0].message, "float literal '0.1' can't be represented exactly");
+		assert_eq!(warnings[0].span, Some(Span::new(8, 11)));
+		assert_eq!(warnings[1].message, "float literal '0.3' can't be represented exactly");
+
+		let src = format!("0.{}1", "0".repeat(310));
+		let tokens = tokenize(&src).unwrap();
+		let ast = parse(&src, &tokens).unwrap();
+		let options = BindOptions { warn_float_precision: true, ..Default::default() };
+		let mut bind = Check::with_options(&src, &ast, options);
+		bind.walk(&ast.root);
+		assert!(bind.report().warnings()[0].message.ends_with("is subnormal"));
+
+		let options = BindOptions { warn_float_precision: true, strict: true, ..Default::default() };
+		let report = Check::with_options(&src, &ast, options).build().unwrap_err();
+		assert!(report.errors()[0].message.ends_with("is subnormal"));
+	}
+
+	#[test]
+	fn array() {
+		let src = "let a = [1, 2, 3]; let b: [Float] = [1, 2.5]; let c: [Int] = []; fn len (xs: [Int]) => 0; let d = len([])";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert_eq!(bind.signature("a").unwrap(), "[Int]");
+		assert_eq!(bind.signature("b").unwrap(), "[Float]");
+		assert_eq!(bind.signature("c").unwrap(), "[Int]");
+		assert_eq!(bind.signature("d").unwrap(), "Int");
+
+		let src = "[1, true]";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let report = Check::new(src, &ast).build().unwrap_err();
+		assert_eq!(report.errors()[0].message, "type mismatch: expected 'Int', found 'Bool'");
+
+		let src = "let e = []; let f: [Bool] = e";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert_eq!(bind.signature("f").unwrap(), "[Bool]");
+
+		// indexing gives an element
+		let src = "let a = [1, 2]; let b = a[0]; let f = fn (xs) => xs[1]";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert_eq!(bind.signature("b").unwrap(), "Int");
+		assert_eq!(bind.signature("f").unwrap(), "forall a. ([a]) -> a");
+
+		for (src, message) in [
+			("let a = [1, 2]; let b: Bool = a[0]", "type mismatch: expected 'Bool', found 'Int'"),
+			("let a = 1; a[0]", "type mismatch: expected '[?1]', found 'Int'"),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let report = Check::new(src, &ast).build().unwrap_err();
+			assert_eq!(report.errors()[0].message, message, "{}", src);
+		}
+	}
+
+	#[test]
+	fn literal_suffix() {
+		let src = "let a = 10i64; let b = 200u8; let c = 3.14f64; let d: u8 = 1; let e = 2.5f32";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert_eq!(bind.signature("a").unwrap(), "Int");
+		assert_eq!(bind.signature("b").unwrap(), "u8");
+		assert_eq!(bind.signature("c").unwrap(), "Float");
+		assert_eq!(bind.signature("e").unwrap(), "Float");
+
+		// a negated literal takes the expected type like any other
+		let src = "let a: i8 = -1; let b: Float = -1; let c: Float = (-1); let d: i8 = -(2); let e: u8 = 1; let f = e + -0";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert_eq!(bind.signature("a").unwrap(), "i8");
+		assert_eq!(bind.signature("c").unwrap(), "Float");
+		assert_eq!(bind.signature("f").unwrap(), "u8");
+
+		// down to each type's minimum
+		let src = "let a = -128i8; let b: i16 = -(32768); let c = -9223372036854775808";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+
+		for suffix in SUFFIXES {
+			assert!(super::builtin_type(suffix).is_some(), "{}", suffix);
+		}
+
+		for (src, message, span) in [
+			("3.14i32", "integer suffix 'i32' on float literal '3.14i32'", Span::new(0, 7)),
+			("10f64", "float suffix 'f64' on integer literal '10f64'", Span::new(0, 5)),
+			("1f32", "float suffix 'f32' on integer literal '1f32'", Span::new(0, 4)),
+			("300u8", "integer literal out of range for 'u8'", Span::new(0, 5)),
+			("let x: u8 = -1", "integer literal out of range for 'u8'", Span::new(13, 14)),
+			("-129i8", "integer literal out of range for 'i8'", Span::new(1, 6)),
+			("-9223372036854775809", "integer literal out of range for 'Int'", Span::new(1, 20)),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let report = Check::new(src, &ast).build().unwrap_err();
+			assert_eq!(report.errors()[0].message, message, "{}", src);
+			assert_eq!(report.errors()[0].span, Some(span), "{}", src);
+		}
+
+		// a suffix fixes the type, so the literal doesn't take the expected one
+		let src = "let x: u8 = 1i64";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let report = Check::new(src, &ast).build().unwrap_err();
+		assert_eq!(report.errors()[0].message, "type mismatch: expected 'u8', found 'Int'");
+	}
+
+	#[test]
+	fn binding_type() {
+		let src = "let x = 1 + 2; export let (a, b) = (true, x); fn f () => x; let x = 2.0";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+
+		assert_eq!(bind.binding_type("x"), Some(ResolvedType::Float));
+		assert_eq!(bind.binding_type("a"), Some(ResolvedType::Bool));
+		assert!(matches!(bind.binding_type("f"), Some(ResolvedType::Fn(..))));
+		assert_eq!(bind.binding_type("y"), None);
+
+		assert_eq!(bind.binding_span("x"), Some(Span::new(64, 65)));
+		assert_eq!(bind.binding_span("b"), Some(Span::new(30, 31)));
+		assert_eq!(bind.binding_span("f"), Some(Span::new(49, 50)));
+		assert_eq!(bind.binding_span("y"), None);
+
+		let src = "let x = 1 + 2";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert_eq!(bind.binding_type("x"), Some(ResolvedType::Int));
+		assert_eq!(bind.binding_span("x"), Some(Span::new(4, 5)));
+	}
+
+	#[test]
+	fn empty() {
+		for src in ["", "   ", "// just a comment"] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let tst = Check::new(src, &ast).build().unwrap();
+			assert_eq!(tst.ty(&tst.root), Some(&Type::Unit), "{:?}", src);
+		}
+	}
+
+	#[test]
+	fn break_value() {
+		let src = "let a = loop { break 1 }; let b = 'outer: loop { while true { break 'outer 2.5 } }; let c = loop { if a == 1 break; break };";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert_eq!(bind.signature("a").unwrap(), "Int");
+		assert_eq!(bind.signature("b").unwrap(), "Float");
+		assert_eq!(bind.signature("c").unwrap(), "Unit");
+
+		for (src, message) in [
+			("loop { break 1; break true }", "type mismatch: expected 'Int', found 'Bool'"),
+			("loop { break 1; break }", "type mismatch: expected 'Int', found 'Unit'"),
+			("while true { break 1 }", "only 'loop' can be broken out of with a value"),
+			("break 1", "'break' outside of a loop"),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let report = Check::new(src, &ast).build().unwrap_err();
+			assert_eq!(report.errors()[0].message, message, "{}", src);
+		}
+	}
+
+	#[test]
+	fn resolved_type() {
+		let src = "let f = fn (a: Int, g) => g(a) > 1.0";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+
+		let g = ResolvedType::Fn(
+			Box::new(ResolvedType::Tuple(vec![ResolvedType::Int])),
+			Box::new(ResolvedType::Float),
+		);
+		assert_eq!(
+			bind.binding_type("f"),
+			Some(ResolvedType::Fn(
+				Box::new(ResolvedType::Tuple(vec![ResolvedType::Int, g])),
+				Box::new(ResolvedType::Bool),
+			)),
+		);
+	}
+
+	#[test]
+	fn parameter_inference() {
+		let src = "let add = fn (a, b) => a + b; let scale = fn (a, b: Float) => a * b; let half = fn (a) => a / 2; let same = fn (a, b) => a == b";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert_eq!(bind.signature("add").unwrap(), "(Int, Int) -> Int");
+		assert_eq!(bind.signature("scale").unwrap(), "(Float, Float) -> Float");
+		assert_eq!(bind.signature("half").unwrap(), "(Int) -> Int");
+		// equality works on anything, so it leaves the parameters generic
+		assert_eq!(bind.signature("same").unwrap(), "forall a. (a, a) -> Bool");
+
+		let src = "let add = fn (a, b) => a + b; add(true, false)";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let report = Check::new(src, &ast).build().unwrap_err();
+		assert_eq!(report.errors()[0].message, "type mismatch: expected 'Int', found 'Bool'");
+	}
+
+	#[test]
+	fn namespaces() {
+		for (src, message, span) in [
+			("let x = Int", "expected a value, found type 'Int'", Span::new(8, 11)),
+			("type Id = Int; let x = Id + 1", "expected a value, found type 'Id'", Span::new(23, 25)),
+			("let foo = 1; let x: foo = 1", "expected a type, found value 'foo'", Span::new(20, 23)),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let report = Check::new(src, &ast).build().unwrap_err();
+			assert_eq!(report.errors()[0].message, message, "{}", src);
+			assert_eq!(report.errors()[0].span, Some(span), "{}", src);
+		}
+
+		// a value can still share its name with a type
+		let src = "let Int = 1; let x: Int = Int";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		assert!(Check::new(src, &ast).build().is_ok());
+	}
+
+	#[test]
+	fn multiple_return() {
+		let src = "fn divmod (a, b) => (a / b, a % b); let (q, r) = divmod(7, 2); let ((x, y), z) = (divmod(1, 1), 2.5)";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert_eq!(bind.signature("divmod").unwrap(), "(Int, Int) -> (Int, Int)");
+		assert_eq!(bind.signature("q").unwrap(), "Int");
+		assert_eq!(bind.signature("r").unwrap(), "Int");
+		assert_eq!(bind.signature("y").unwrap(), "Int");
+		assert_eq!(bind.signature("z").unwrap(), "Float");
+
+		let src = "fn divmod (a, b) => (a / b, a % b); let (q, r, s) = divmod(7, 2)";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		assert!(Check::new(src, &ast).build().is_err());
+	}
+
+	#[test]
+	fn else_if() {
+		let src = "let x = if true 1 else if false 2 else 3; if x == 1 { 1 } else if x == 2 { true }";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert_eq!(bind.signature("x").unwrap(), "Int");
+
+		for (src, message) in [
+			("let x = if true 1 else if false 2", "'if' used as a value must have an 'else' branch"),
+			("let x = if true 1 else if false 2 else true", "type mismatch: expected 'Int', found 'Bool'"),
+			("let x: Bool = if true 1 else if false true else false", "type mismatch: expected 'Bool', found 'Int'"),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let report = Check::new(src, &ast).build().unwrap_err();
+			assert_eq!(report.errors()[0].message, message, "{}", src);
+		}
+
+		// a missing `else` is reported at the `if` it's missing from
+		for (src, span) in [
+			("let x = if true 1 else if false 2", Span::new(23, 33)),
+			("let x = if let a = 1 a", Span::new(8, 22)),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let report = Check::new(src, &ast).build().unwrap_err();
+			assert_eq!(report.errors()[0].message, "'if' used as a value must have an 'else' branch", "{}", src);
+			assert_eq!(report.errors()[0].span, Some(span), "{}", src);
+		}
+
+		// a long chain is walked without recursing into every `else`
+		let mut src = String::from("let x = 7; let y = ");
+		for i in 0..2000 {
+			src.push_str(&format!("if x == {} {} else ", i, i * 2));
+		}
+		src.push_str("0; y");
+		let tokens = tokenize(&src).unwrap();
+		let ast = parse(&src, &tokens).unwrap();
+		assert!(Check::new(&src, &ast).build().is_ok());
+		assert_eq!(crate::eval::eval(&ast).unwrap(), crate::eval::Value::Int(14));
+	}
+
+	#[test]
+	fn fields() {
+		let src = "let r = { a = 1, b = (true, 2.5) }; let x = r.a; let y = r.b.1; let f = fn () => r; let z = f().b.0";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert_eq!(bind.signature("x").unwrap(), "Int");
+		assert_eq!(bind.signature("y").unwrap(), "Float");
+		assert_eq!(bind.signature("z").unwrap(), "Bool");
+
+		for (src, message) in [
+			("let r = { a = 1, }; r.b", "no field 'b' on type '{ a: Int }'"),
+			("let t = (1, 2); t.2", "no field '2' on type '(Int, Int)'"),
+			("let f = fn (r) => r.a", "the type must be known here to access its field 'a'"),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let report = Check::new(src, &ast).build().unwrap_err();
+			assert_eq!(report.errors()[0].message, message, "{}", src);
+		}
+	}
+
+	#[test]
+	fn enums() {
+		let src = "enum Opt { Some(Int), None }; let a = Some(1); let b = None; let c: Opt = if true a else b; enum List { Cons(Int, List), Empty }; let d = Cons(1, Cons(2, Empty))";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let mut bind = Check::new(src, &ast);
+		bind.walk(&ast.root);
+		assert!(bind.report().ok(), "{}", bind.report());
+		assert_eq!(bind.signature("Some").unwrap(), "(Int) -> Opt");
+		assert_eq!(bind.signature("None").unwrap(), "Opt");
+		assert_eq!(bind.signature("a").unwrap(), "Opt");
+		assert_eq!(bind.signature("d").unwrap(), "List");
+		assert_eq!(bind.binding_type("b"), Some(ResolvedType::Enum("Opt".to_string())));
+
+		for (src, message) in [
+			("enum Opt { Some(Int), None }; Some(true)", "type mismatch: expected 'Int', found 'Bool'"),
+			("enum A { X }; enum B { Y }; let a: A = Y", "type mismatch: expected 'A', found 'B'"),
+			("enum E { V(Nope) }", "unknown type 'Nope'"),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let report = Check::new(src, &ast).build().unwrap_err();
+			assert_eq!(report.errors()[0].message, message, "{}", src);
+		}
+	}
+
+	#[test]
+	fn stable_variables() {
+		let messages = |src: &str| {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let report = Check::new(src, &ast).build().unwrap_err();
+			report.errors().iter().map(|v| v.message.clone()).collect::<Vec<_>>()
+		};
+
+		let src = "let g = fn (x, y) => x(y, 1); g(true, nil)";
+		assert_eq!(messages(src), messages(src));
+		assert_eq!(messages(src), vec!["type mismatch: expected '(?1, Int) -> ?2', found 'Bool'"]);
+
+		// inference elsewhere doesn't renumber them
+		let other = format!("let id = fn (a) => a; let p = (id(1), id(nil), [[]]); {}", src);
+		assert_eq!(messages(&other), messages(src));
+	}
+}
@@ -0,0 +1,479 @@
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{parse::{Ast, Node, NodeIndex, Pattern}, report::Report, token::{Span, TT}};
+
+
+// scopes are shared, so a closure sees bindings made after it was created,
+// including its own. a recursive function therefore holds onto its scope
+// forever, which is fine for something this short lived
+type Scope = Rc<RefCell<HashMap<String, Value>>>;
+
+/// a function, along with the scopes it was created in
+pub struct Closure {
+	node: NodeIndex,
+	scopes: Vec<Scope>,
+}
+impl std::fmt::Debug for Closure {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		// the scopes may well contain this closure
+		write!(f, "Closure({})", self.node.0)
+	}
+}
+impl PartialEq for Closure {
+	fn eq(&self, other: &Self) -> bool {
+		std::ptr::eq(self, other)
+	}
+}
+
+/// the result of evaluating an expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+	Unit,
+	Int(i64),
+	Float(f64),
+	Bool(bool),
+	Tuple(Vec<Value>),
+	Closure(Rc<Closure>),
+}
+impl Value {
+	/// the name of the value's type, as the binder would display it
+	pub fn type_name(&self) -> &'static str {
+		match self {
+			Value::Unit => "Unit",
+			Value::Int(_) => "Int",
+			Value::Float(_) => "Float",
+			Value::Bool(_) => "Bool",
+			Value::Tuple(_) => "Tuple",
+			Value::Closure(_) => "Fn",
+		}
+	}
+}
+impl std::fmt::Display for Value {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Value::Unit => write!(f, "()"),
+			Value::Int(v) => write!(f, "{}", v),
+			Value::Float(v) => write!(f, "{}", v),
+			Value::Bool(v) => write!(f, "{}", v),
+			Value::Tuple(items) => {
+				let items = items.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+				write!(f, "({})", items.join(", "))
+			}
+			Value::Closure(_) => write!(f, "<fn>"),
+		}
+	}
+}
+
+
+// how deep calls can nest before evaluation gives up. each level takes a
+// few `eval` frames, and this keeps a debug build inside a 2 MiB thread stack
+const MAX_DEPTH: usize = 100;
+
+struct Eval<'a> {
+	src: &'a str,
+	ast: &'a Ast<'a>,
+	// the bindings of each scope, innermost last. unlike the binder's
+	// scopes these aren't copies, so assignment can reach outer bindings
+	scopes: Vec<Scope>,
+	// whether this is a `const` initializer, which can only see constants
+	constant: bool,
+	// how many calls deep evaluation is, so runaway recursion is an error
+	// rather than a stack overflow
+	depth: usize,
+}
+impl<'a> Eval<'a> {
+	fn lookup(&self, name: &str) -> Option<&Scope> {
+		self.scopes.iter().rev().find(|v| v.borrow().contains_key(name))
+	}
+
+	fn scope_begin(&mut self) {
+		self.scopes.push(Rc::new(RefCell::new(HashMap::new())));
+	}
+
+	fn define(&mut self, name: &str, value: Value) {
+		self.scopes.last().unwrap().borrow_mut().insert(name.to_string(), value);
+	}
+
+	// binds a pattern's names to the parts of a value. the binder has
+	// already checked that the shapes match
+	fn destructure(&mut self, index: &NodeIndex, pattern: &Pattern, value: Value) -> Result<(), (Span, String)> {
+		match (pattern, value) {
+			(Pattern::Identifier { name }, value) => self.define(name.get(self.src), value),
+			(Pattern::Tuple { items, .. }, Value::Tuple(values)) if items.len() == values.len() => {
+				for (item, value) in items.iter().zip(values) {
+					self.destructure(index, item, value)?;
+				}
+			}
+			(Pattern::Tuple { items, .. }, value) => {
+				return self.fail(index, format!("cannot destructure {} into {} names", value.type_name(), items.len()));
+			}
+		}
+		Ok(())
+	}
+
+	fn statements(&mut self, expr: &[NodeIndex]) -> Result<Value, (Span, String)> {
+		// like the binder, named functions are bound before the statements
+		// run so they can be called from earlier ones
+		for v in expr {
+			if let Node::Fn { name: Some(_), .. } = self.ast.get(v) {
+				self.eval(v)?;
+			}
+		}
+
+		let mut value = Value::Unit;
+		for v in expr {
+			value = self.eval(v)?;
+		}
+		Ok(value)
+	}
+
+	fn fail<T>(&self, index: &NodeIndex, msg: String) -> Result<T, (Span, String)> {
+		Err((self.ast.span(index), msg))
+	}
+
+	fn eval(&mut self, index: &NodeIndex) -> Result<Value, (Span, String)> {
+		match self.ast.get(index) {
+			// like the binder, the module's bindings live in the outermost scope
+			Node::Module { root } => match self.ast.get(root) {
+				Node::Block { expr } => self.statements(expr),
+				_ => self.eval(root),
+			},
+
+			Node::Block { expr } => {
+				self.scope_begin();
+				let value = self.statements(expr);
+				self.scopes.pop();
+				value
+			}
+
+			Node::Identifier { name } => {
+				let name = name.get(self.src);
+				match self.lookup(name) {
+					Some(scope) => Ok(scope.borrow()[name].clone()),
+					None if self.constant => self.fail(index, format!("'{}' is not a constant", name)),
+					None => self.fail(index, format!("undefined identifier '{}'", name)),
+				}
+			}
+
+			Node::Bool { value } => Ok(Value::Bool(*value)),
+			Node::Integer { value } => match self.ast.tokens.int_value(value).and_then(|v| i64::try_from(v).ok()) {
+				Some(value) => Ok(Value::Int(value)),
+				None => self.fail(index, "integer literal out of range for 'Int'".to_string()),
+			},
+			Node::Float { value } => match self.ast.tokens.float_value(value) {
+				Some(value) => Ok(Value::Float(value)),
+				None => self.fail(index, "malformed float literal".to_string()),
+			},
+
+			Node::Group { expr } => self.eval(expr),
+			Node::Tuple { items } => {
+				let items = items.iter().map(|v| self.eval(v)).collect::<Result<_, _>>()?;
+				Ok(Value::Tuple(items))
+			}
+
+			// `i64::MIN` can only be written negated, since its magnitude is
+			// one past `i64::MAX`
+			Node::Unary { op, right } if op.kind == TT::Minus && matches!(
+				self.ast.get(right),
+				Node::Integer { value } if self.ast.tokens.int_value(value) == Some(1 << 63),
+			) => {
+				Ok(Value::Int(i64::MIN))
+			}
+			Node::Unary { op, right } => {
+				let right = self.eval(right)?;
+				match (op.kind, right) {
+					(TT::Minus, Value::Int(v)) => match v.checked_neg() {
+						Some(v) => Ok(Value::Int(v)),
+						None => self.fail(index, "integer overflow".to_string()),
+					},
+					(TT::Minus, Value::Float(v)) => Ok(Value::Float(-v)),
+					(TT::Bang, Value::Bool(v)) => Ok(Value::Bool(!v)),
+					(_, right) => self.fail(index, format!(
+						"no operator '{}' for {}",
+						op.kind.symbol().unwrap_or_default(), right.type_name(),
+					)),
+				}
+			}
+
+			Node::Binary { left, op, right } => {
+				let left = self.eval(left)?;
+				let right = self.eval(right)?;
+				self.binary(index, op.kind, left, right)
+			}
+
+			// an `else if` chain is followed in a loop rather than recursively
+			Node::If { .. } => {
+				let mut current = index.clone();
+				while let Node::If { condition, then_branch, else_branch, .. } = self.ast.get(&current) {
+					let condition = match self.eval(condition)? {
+						Value::Bool(v) => v,
+						other => return self.fail(&current, format!("expected a condition of type Bool, found {}", other.type_name())),
+					};
+					match (condition, else_branch) {
+						(true, _) => return self.eval(then_branch),
+						(false, Some(else_branch)) => current = else_branch.clone(),
+						(false, None) => return Ok(Value::Unit),
+					}
+				}
+				self.eval(&current)
+			}
+
+			Node::Let { pattern, expr, .. } => {
+				let value = self.eval(expr)?;
+				self.destructure(index, pattern, value)?;
+				Ok(Value::Unit)
+			}
+
+			// the binder has already checked the target is an identifier
+			Node::Assign { target, value, .. } => {
+				let value = self.eval(value)?;
+				let Node::Identifier { name } = self.ast.get(target) else {
+					return self.fail(target, "invalid assignment target".to_string());
+				};
+				let name = name.get(self.src);
+				match self.lookup(name) {
+					Some(scope) => scope.borrow_mut().insert(name.to_string(), value.clone()),
+					None => return self.fail(target, format!("undefined identifier '{}'", name)),
+				};
+				Ok(value)
+			}
+
+			Node::Const { name, expr, .. } => {
+				let value = self.eval(expr)?;
+				self.define(name.get(self.src), value);
+				Ok(Value::Unit)
+			}
+
+			Node::TypeAlias { .. } => Ok(Value::Unit),
+			Node::Attribute { item, .. } => self.eval(item),
+
+			Node::Fn { variadic: true, .. } => self.fail(index, "cannot evaluate rest parameters".to_string()),
+			Node::Fn { name, .. } => {
+				let closure = Value::Closure(Rc::new(Closure {
+					node: index.clone(),
+					scopes: self.scopes.clone(),
+				}));
+				if let Some(name) = name {
+					self.define(name.get(self.src), closure.clone());
+				}
+				Ok(closure)
+			}
+
+			Node::Call { expr, args, .. } => self.call(index, expr, args),
+
+			other => self.fail(index, format!("cannot evaluate {:?} nodes", other.kind())),
+		}
+	}
+
+	// kept out of `eval` so its locals don't add to every `eval` frame
+	fn call(&mut self, index: &NodeIndex, expr: &NodeIndex, args: &[NodeIndex]) -> Result<Value, (Span, String)> {
+		let closure = match self.eval(expr)? {
+			Value::Closure(closure) => closure,
+			other => return self.fail(expr, format!("cannot call a value of type {}", other.type_name())),
+		};
+		let Node::Fn { args: params, expr: body, .. } = self.ast.get(&closure.node) else {
+			unreachable!("closures are only made from Fn nodes");
+		};
+		if params.len() != args.len() {
+			let callee = match self.ast.get(expr) {
+				Node::Identifier { name } => format!("function '{}'", name.get(self.src)),
+				_ => "function".to_string(),
+			};
+			return self.fail(index, format!(
+				"{} expects {} argument{}, found {}",
+				callee, params.len(), if params.len() == 1 { "" } else { "s" }, args.len(),
+			));
+		}
+
+		let mut values = Vec::new();
+		for v in args {
+			values.push(self.eval(v)?);
+		}
+
+		if self.depth == MAX_DEPTH {
+			return self.fail(index, "recursion limit exceeded".to_string());
+		}
+		let scopes = std::mem::replace(&mut self.scopes, closure.scopes.clone());
+		self.scope_begin();
+		for ((name, _), value) in params.iter().zip(values) {
+			self.define(name.get(self.src), value);
+		}
+		self.depth += 1;
+		let value = self.eval(body);
+		self.depth -= 1;
+		self.scopes = scopes;
+		value
+	}
+
+	fn binary(&self, index: &NodeIndex, op: TT, left: Value, right: Value) -> Result<Value, (Span, String)> {
+		let value = match (left, right) {
+			(Value::Int(x), Value::Int(y)) => {
+				let result = match op {
+					TT::Plus => x.checked_add(y),
+					TT::Minus => x.checked_sub(y),
+					TT::Star => x.checked_mul(y),
+					TT::Slash | TT::Percent if y == 0 => return self.fail(index, "division by zero".to_string()),
+					TT::Slash => x.checked_div(y),
+					TT::Percent => x.checked_rem(y),
+					_ => return Ok(Value::Bool(compare(op, &x, &y))),
+				};
+				match result {
+					Some(v) => Value::Int(v),
+					None => return self.fail(index, "integer overflow".to_string()),
+				}
+			}
+			(Value::Float(x), Value::Float(y)) => match op {
+				TT::Plus => Value::Float(x + y),
+				TT::Minus => Value::Float(x - y),
+				TT::Star => Value::Float(x * y),
+				TT::Slash => Value::Float(x / y),
+				TT::Percent => Value::Float(x % y),
+				_ => Value::Bool(compare(op, &x, &y)),
+			},
+			(Value::Bool(x), Value::Bool(y)) if matches!(op, TT::EqualEqual | TT::BangEqual) => {
+				Value::Bool(compare(op, &x, &y))
+			}
+			(left, right) => return self.fail(index, format!(
+				"no operator '{}' for {} and {}",
+				op.symbol().unwrap_or_default(), left.type_name(), right.type_name(),
+			)),
+		};
+		Ok(value)
+	}
+}
+
+fn compare<T: PartialOrd>(op: TT, x: &T, y: &T) -> bool {
+	match op {
+		TT::EqualEqual => x == y,
+		TT::BangEqual => x != y,
+		TT::Lesser => x < y,
+		TT::LesserEqual => x <= y,
+		TT::Greater => x > y,
+		TT::GreaterEqual => x >= y,
+		_ => unreachable!("only comparisons are left after arithmetic"),
+	}
+}
+
+
+/// evaluates a tree, returning the value of its last expression
+pub fn eval(ast: &Ast) -> Result<Value, Report> {
+	let mut eval = Eval {
+		src: &ast.tokens.src,
+		ast,
+		scopes: Vec::new(),
+		constant: false,
+		depth: 0,
+	};
+	eval.scope_begin();
+	eval.eval(&ast.root).map_err(|(span, msg)| {
+		let mut report = Report::new();
+		report.error_at(span, msg);
+		report
+	})
+}
+
+/// evaluates the initializer of a `const`, which can only refer to the
+/// constants in `consts`
+pub fn eval_const(ast: &Ast, index: &NodeIndex, consts: &HashMap<&str, Value>) -> Result<Value, Report> {
+	let scope = consts.iter().map(|(k, v)| (k.to_string(), v.clone())).collect();
+	let mut eval = Eval {
+		src: &ast.tokens.src,
+		ast,
+		scopes: vec![Rc::new(RefCell::new(scope))],
+		constant: true,
+		depth: 0,
+	};
+	eval.eval(index).map_err(|(span, msg)| {
+		let mut report = Report::new();
+		report.error_at(span, msg);
+		report
+	})
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::{eval::{eval, Value}, parse, tokenize};
+
+	#[test]
+	fn arithmetic() {
+		for (src, value) in [
+			("1 + 2 * 3", Value::Int(7)),
+			("(1 + 2) * 3", Value::Int(9)),
+			("7 % 4 - -1", Value::Int(4)),
+			("1.5 * 2.0", Value::Float(3.0)),
+			("1 < 2", Value::Bool(true)),
+			("!(1 == 1) != false", Value::Bool(false)),
+			("if true 1 else 2", Value::Int(1)),
+			("if 1 > 2 1 else 2", Value::Int(2)),
+			("if false 1", Value::Unit),
+			("-9223372036854775808", Value::Int(i64::MIN)),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			assert_eq!(eval(&ast).unwrap(), value, "{}", src);
+		}
+	}
+
+	#[test]
+	fn bindings() {
+		for (src, value) in [
+			("{ let x = 2; x * x }", Value::Int(4)),
+			("let x = 1; x = 2; x", Value::Int(2)),
+			("mut x = 1; { let y = x = x + 1; let x = 10; x = 20 }; x", Value::Int(2)),
+			("mut a = 0; mut b = 0; a = b = 3; a + b", Value::Int(6)),
+			("let x = 1", Value::Unit),
+			("const X = 2; const Y = X * 3; Y", Value::Int(6)),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			assert_eq!(eval(&ast).unwrap(), value, "{}", src);
+		}
+	}
+
+	#[test]
+	fn functions() {
+		for (src, value) in [
+			("let add = fn (a, b) => a + b; add(2, 3)", Value::Int(5)),
+			("let fact = fn (n) => if n < 2 1 else n * fact(n - 1); fact(5)", Value::Int(120)),
+			("let k = 2; let f = fn (x) => x * k; { let k = 3; f(5) }", Value::Int(10)),
+			("let adder = fn (a) => fn (b) => a + b; adder(1)(2)", Value::Int(3)),
+			("mut n = 0; let bump = fn () => n = n + 1; bump(); bump(); n", Value::Int(2)),
+			("{ fn fact (n) => if n < 2 1 else n * fact(n - 1); fact(5) }", Value::Int(120)),
+			("fn f (n) => if n == 0 0 else 1 + f(n - 1); f(90)", Value::Int(90)),
+			("mut x = 0; { x = 5; x + 1 }", Value::Int(6)),
+			("let apply = fn (x, f) => f(x); apply(2) { v => v * 3 }", Value::Int(6)),
+			("", Value::Unit),
+			("  // just a comment", Value::Unit),
+			("let x = twice(3); fn twice (n) => n * 2; x", Value::Int(6)),
+			("fn divmod (a, b) => (a / b, a % b); let (q, r) = divmod(7, 2); q * 10 + r", Value::Int(31)),
+			("let swap = fn (a, b) => (b, a); swap(1, true)", Value::Tuple(vec![Value::Bool(true), Value::Int(1)])),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			assert_eq!(eval(&ast).unwrap(), value, "{}", src);
+		}
+	}
+
+	#[test]
+	fn errors() {
+		for (src, message) in [
+			("1 / 0", "division by zero"),
+			("9223372036854775807 + 1", "integer overflow"),
+			("1 + true", "no operator '+' for Int and Bool"),
+			("let f = fn (a, b) => a; f(1)", "function 'f' expects 2 arguments, found 1"),
+			("let x = 1; x(2)", "cannot call a value of type Int"),
+			("[1, 2]", "cannot evaluate Array nodes"),
+			("{ let x = 1 }; x", "undefined identifier 'x'"),
+			("let x = 1; x / (x - 1)", "division by zero"),
+			("fn f (n) => f(n); f(1)", "recursion limit exceeded"),
+			("fn f (n) => if n == 0 0 else 1 + f(n - 1); f(100000)", "recursion limit exceeded"),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let report = eval(&ast).unwrap_err();
+			assert_eq!(report.errors()[0].message, message, "{}", src);
+		}
+	}
+}
@@ -0,0 +1,203 @@
+
+use crate::{parse::{Ast, Node, NodeIndex}, report::Report, token::TT};
+
+
+// folds a condition down to a constant, if it is made only of literals
+fn constant(ast: &Ast, index: &NodeIndex) -> Option<bool> {
+	match ast.get(index) {
+		Node::Bool { value } => Some(*value),
+		Node::Group { expr } => constant(ast, expr),
+		Node::Unary { op, right } if op.kind == TT::Bang => constant(ast, right).map(|v| !v),
+		Node::Binary { left, op, right } => {
+			if let (Some(left), Some(right)) = (constant(ast, left), constant(ast, right)) {
+				return match op.kind {
+					TT::EqualEqual => Some(left == right),
+					TT::BangEqual => Some(left != right),
+					_ => None,
+				};
+			}
+			let (Node::Integer { value: left }, Node::Integer { value: right }) = (ast.get(left), ast.get(right)) else {
+				return None;
+			};
+			let left = ast.tokens.int_value(left)?;
+			let right = ast.tokens.int_value(right)?;
+			match op.kind {
+				TT::EqualEqual => Some(left == right),
+				TT::BangEqual => Some(left != right),
+				TT::Lesser => Some(left < right),
+				TT::LesserEqual => Some(left <= right),
+				TT::Greater => Some(left > right),
+				TT::GreaterEqual => Some(left >= right),
+				_ => None,
+			}
+		}
+		_ => None,
+	}
+}
+
+fn constant_conditions(ast: &Ast, report: &mut Report) {
+	for node in &ast.nodes {
+		if let Node::While { op, condition, .. } = node {
+			if constant(ast, condition) == Some(false) {
+				report.warn_at(op.span(), "condition is always false, the loop body never runs".to_string());
+			}
+			continue;
+		}
+
+		let Node::If { op, condition, else_branch, .. } = node else {
+			continue;
+		};
+		match (constant(ast, condition), else_branch) {
+			(Some(true), Some(_)) => report.warn_at(
+				op.span(),
+				"condition is always true, the else branch is never taken".to_string(),
+			),
+			(Some(true), None) => report.warn_at(
+				op.span(),
+				"condition is always true".to_string(),
+			),
+			(Some(false), _) => report.warn_at(
+				op.span(),
+				"condition is always false, the then branch is never taken".to_string(),
+			),
+			(None, _) => (),
+		}
+	}
+}
+
+// whether two subtrees are written the same, looking through groups. calls
+// are left out, since two calls with the same arguments can still differ
+fn same(ast: &Ast, a: &NodeIndex, b: &NodeIndex) -> bool {
+	let src = &*ast.tokens.src;
+	match (ast.get(a), ast.get(b)) {
+		(Node::Group { expr }, _) => same(ast, expr, b),
+		(_, Node::Group { expr }) => same(ast, a, expr),
+		(Node::Identifier { name: a }, Node::Identifier { name: b })
+		| (Node::Integer { value: a }, Node::Integer { value: b })
+		| (Node::Float { value: a }, Node::Float { value: b }) => a.get(src) == b.get(src),
+		(Node::Bool { value: a }, Node::Bool { value: b }) => a == b,
+		(Node::Nil, Node::Nil) => true,
+		(Node::Field { expr: a, name: name_a, .. }, Node::Field { expr: b, name: name_b, .. }) => {
+			name_a.get(src) == name_b.get(src) && same(ast, a, b)
+		}
+		(Node::Unary { op: op_a, right: a }, Node::Unary { op: op_b, right: b }) => {
+			op_a.kind == op_b.kind && same(ast, a, b)
+		}
+		(
+			Node::Binary { left: left_a, op: op_a, right: right_a },
+			Node::Binary { left: left_b, op: op_b, right: right_b },
+		) => op_a.kind == op_b.kind && same(ast, left_a, left_b) && same(ast, right_a, right_b),
+		_ => false,
+	}
+}
+
+fn self_comparisons(ast: &Ast, report: &mut Report) {
+	for node in &ast.nodes {
+		let Node::Binary { left, op, right } = node else {
+			continue;
+		};
+		let comparison = matches!(
+			op.kind,
+			TT::EqualEqual | TT::BangEqual | TT::Lesser | TT::LesserEqual | TT::Greater | TT::GreaterEqual,
+		);
+		if comparison && same(ast, left, right) {
+			let span = ast.span(left);
+			report.warn_at(
+				op.span(),
+				format!("'{}' is compared to itself", &ast.tokens.src[span.start as usize..span.end as usize]),
+			);
+		}
+	}
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LintOptions {
+	/// report findings as errors rather than warnings
+	pub strict: bool,
+}
+
+/// runs every lint over the tree, reporting findings as warnings
+pub fn lint(ast: &Ast) -> Report {
+	lint_with(ast, &LintOptions::default())
+}
+
+pub fn lint_with(ast: &Ast, options: &LintOptions) -> Report {
+	let mut report = Report::new();
+	report.set_strict(options.strict);
+	constant_conditions(ast, &mut report);
+	self_comparisons(ast, &mut report);
+	report
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::{lint::{lint, lint_with, LintOptions}, parse, tokenize};
+
+	#[test]
+	fn constant_condition() {
+		let src = "if true 1 else 2";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let report = lint(&ast);
+		assert_eq!(report.warnings()[0].message, "condition is always true, the else branch is never taken");
+
+		let src = "if !(1 < 2) 1 else 2";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let report = lint(&ast);
+		assert_eq!(report.warnings()[0].message, "condition is always false, the then branch is never taken");
+	}
+
+	#[test]
+	fn constant_loop_condition() {
+		let src = "while false { }";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let report = lint(&ast);
+		assert_eq!(report.warnings()[0].message, "condition is always false, the loop body never runs");
+	}
+
+	#[test]
+	fn variable_condition() {
+		let src = "let c = true; if c 1 else 2; if c == true 1; while c { }";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let report = lint(&ast);
+		assert!(report.warnings().is_empty());
+	}
+
+	#[test]
+	fn self_comparison() {
+		for (src, message) in [
+			("x == x", "'x' is compared to itself"),
+			("a + 1 < (a + 1)", "'a + 1' is compared to itself"),
+			("-x != -(x)", "'-x' is compared to itself"),
+			("p.x == p.x", "'p.x' is compared to itself"),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let report = lint(&ast);
+			assert_eq!(report.warnings().len(), 1, "{}", src);
+			assert_eq!(report.warnings()[0].message, message, "{}", src);
+		}
+
+		for src in ["x == y", "a + 1 < a + 2", "f() == f()", "x = x", "x + x", "p.x == p.y"] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			assert!(lint(&ast).warnings().is_empty(), "{}", src);
+		}
+	}
+
+	#[test]
+	fn strict() {
+		let src = "if true 1 else 2";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		assert!(lint(&ast).into_result(()).is_ok());
+
+		let report = lint_with(&ast, &LintOptions { strict: true }).into_result(()).unwrap_err();
+		assert_eq!(report.errors().len(), 1);
+		assert!(report.warnings().is_empty());
+	}
+}
@@ -1,405 +1,3075 @@
-
-use crate::{report::Report, token::{Token, TokenStream, TT}};
-
-use std::{iter::Peekable, slice::Iter};
-
-
-#[repr(transparent)]
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct NodeIndex(pub u32);
-
-#[derive(Debug)]
-pub enum Node<'a> {
-	Error,
-	Module {
-		root: NodeIndex,
-	},
-	Block {
-		expr: Vec<NodeIndex>,
-	},
-	Identifier {
-		name: &'a Token,
-	},
-	Bool {
-		value: bool,
-	},
-	Integer {
-		value: &'a Token,
-	},
-	Float {
-		value: &'a Token,
-	},
-	Fn {
-		args: Vec<(&'a Token, Option<NodeIndex>)>,
-		ret: Option<NodeIndex>,
-		expr: NodeIndex,
-	},
-	Group {
-		expr: NodeIndex,
-	},
-	Binary {
-		left: NodeIndex,
-		op: &'a Token,
-		right: NodeIndex,
-	},
-	Unary {
-		op: &'a Token,
-		right: NodeIndex,
-	},
-	Call {
-		op: &'a Token,
-		expr: NodeIndex,
-		args: Vec<NodeIndex>,
-	},
-	If {
-		op: &'a Token,
-		condition: NodeIndex,
-		then_branch: NodeIndex,
-		else_branch: Option<NodeIndex>,
-	},
-	Let {
-		mutable: bool,
-		name: &'a Token,
-		expr: NodeIndex,
-		annotation: Option<NodeIndex>,
-	},
-}
-
-#[derive(Debug)]
-pub struct Ast<'a> {
-	pub tokens: &'a TokenStream<'a>,
-	pub nodes: Vec<Node<'a>>,
-	pub root: NodeIndex,
-}
-impl<'a> Ast<'a> {
-	pub fn get(&self, node: &NodeIndex) -> &Node {
-		&self.nodes[node.0 as usize]
-	}
-}
-
-
-struct Parser<'a> {
-	src: &'a str,
-	tokens: &'a TokenStream<'a>,
-	nodes: Vec<Node<'a>>,
-	iter: Peekable<Iter<'a, Token>>,
-	report: Report,
-}
-impl<'a> Parser<'a> {
-	fn new(src: &'a str, tokens: &'a TokenStream) -> Self {
-		Self {
-			src,
-			tokens,
-			nodes: Vec::new(),
-			iter: tokens.tokens.iter().peekable(),
-			report: Report::new(),
-		}
-	}
-
-	fn add(&mut self, value: Node<'a>) -> NodeIndex {
-		self.nodes.push(value);
-		NodeIndex(self.nodes.len() as u32 - 1)
-	}
-
-	// since there is a TT::Eof at the end of the iterator, it's probably okay
-	// unwrap, as nothing should ever end up consuming TT::Eof
-	fn next(&mut self) -> &'a Token {
-		self.iter.next().unwrap()
-	}
-	fn peek(&mut self) -> &'a Token {
-		self.iter.peek().unwrap()
-	}
-
-	fn catch(&mut self, check: &[TT]) -> Option<&'a Token> {
-		let kind = self.iter.peek()?.kind;
-		if check.iter().find(|v| **v == kind).is_some() {
-			self.iter.next()
-		} else {
-			None
-		}
-	}
-
-	fn build(mut self) -> Result<Ast<'a>, Report> {
-		let root = self.module();
-		if self.report.ok() {
-			Ok(Ast { tokens: self.tokens, nodes: self.nodes, root })
-		} else {
-			Err(self.report)
-		}
-	}
-
-	fn module(&mut self) -> NodeIndex {
-		let root = self.block(|_| false);
-		self.add(Node::Module { root })
-	}
-
-	fn block(&mut self, end: impl Fn(TT) -> bool) -> NodeIndex {
-		let mut expr = Vec::new();
-
-		while let Some(c) = self.iter.peek() {
-			if c.kind == TT::Eof {
-				break;
-			} else if end(c.kind) {
-				self.iter.next();
-				break;
-			}
-			expr.push(self.statement());
-			self.catch(&[TT::SemiColon]);
-		}
-
-		self.add(Node::Block { expr })
-	}
-
-	fn statement(&mut self) -> NodeIndex {
-		if let Some(op) = self.catch(&[TT::Let, TT::Mut]) {
-			let name = self.catch(&[TT::Identifier]).unwrap();
-
-			let annotation =
-				if let Some(_) = self.catch(&[TT::Colon]) {
-					Some(self.type_expression())
-				} else {
-					None
-				};
-			
-			self.catch(&[TT::Equal]).unwrap();
-			
-			let expr = self.expression();
-			
-			self.add(Node::Let { mutable: op.kind == TT::Mut, name, annotation, expr })
-		} else {
-			self.expression()
-		}
-	}
-
-	fn expression(&mut self) -> NodeIndex {
-		self.function()
-	}
-
-	fn function(&mut self) -> NodeIndex {
-		if let Some(_) = self.catch(&[TT::Fn]) {
-			self.catch(&[TT::LParen]).unwrap();
-
-			let mut args = Vec::new();
-			loop {
-				if let Some(_) = self.catch(&[TT::RParen]) {
-					break;
-				}
-				
-				let name = self.catch(&[TT::Identifier]).expect(&format!("found {}", self.peek()));
-
-				let annotation;
-				if let Some(_) = self.catch(&[TT::Colon]) {
-					annotation = Some(self.type_expression());
-				} else {
-					annotation = None;
-				}
-				
-				args.push((name, annotation));
-
-				self.catch(&[TT::Comma]);
-			}
-
-			let ret =
-				if let Some(_) = self.catch(&[TT::Colon]) {
-					Some(self.type_expression())
-				} else {
-					None
-				};
-
-			if let None = self.catch(&[TT::EqualGreater]) {
-				let tt = self.peek();
-				self.report.error(format!("expected '=>', found {:?}", tt));
-			}
-
-			let expr = self.expression();
-
-			self.add(Node::Fn { args, ret, expr })
-		} else {
-			self.jump()
-		}
-	}
-
-	fn jump(&mut self) -> NodeIndex {
-		if let Some(op) = self.catch(&[TT::If]) {
-			let condition = self.equality();
-			
-			let then_branch = self.expression();
-
-			let else_branch =
-				if let Some(_) = self.catch(&[TT::Else]) {
-					Some(self.expression())
-				} else {
-					None
-				};
-			
-			self.add(Node::If { op, condition, then_branch, else_branch })
-		} else {
-			self.equality()
-		}
-	}
-
-	fn equality(&mut self) -> NodeIndex {
-		let mut left = self.term();
-		while let Some(op) = self.catch(&[
-			TT::EqualEqual, TT::BangEqual,
-			TT::Lesser, TT::LesserEqual,
-			TT::Greater, TT::GreaterEqual,
-		]) {
-			let right = self.term();
-			left = self.add(Node::Binary { left, op, right });
-		}
-		left
-	}
-
-	fn term(&mut self) -> NodeIndex {
-		let mut left = self.factor();
-		while let Some(op) = self.catch(&[TT::Plus, TT::Minus]) {
-			let right = self.factor();
-			left = self.add(Node::Binary { left, op, right });
-		}
-		left
-	}
-
-	fn factor(&mut self) -> NodeIndex {
-		let mut left = self.unary();
-		while let Some(op) = self.catch(&[TT::Star, TT::Slash]) {
-			let right = self.unary();
-			left = self.add(Node::Binary { left, op, right });
-		}
-		left
-	}
-
-	fn unary(&mut self) -> NodeIndex {
-		if let Some(op) = self.catch(&[TT::Minus, TT::Bang]) {
-			let right = self.unary();
-			self.add(Node::Unary { op, right })
-		} else {
-			self.call()
-		}
-	}
-
-	fn call(&mut self) -> NodeIndex {
-		let mut expr = self.primary();
-
-		loop {
-			if let Some(_) = self.catch(&[TT::LParen]) {
-				let mut args = vec![];
-				if self.peek().kind != TT::RParen {
-					loop {
-						args.push(self.expression());
-						if let None = self.catch(&[TT::Comma]) {
-							break;
-						}
-					}
-				}
-				let op = self.catch(&[TT::RParen]).unwrap();
-				expr = self.add(Node::Call { op, expr, args })
-			} else {
-				break;
-			}
-		}
-
-		expr
-	}
-
-	fn primary(&mut self) -> NodeIndex {
-		let kind = self.peek().kind;
-
-		match kind {
-			TT::Identifier => {
-				let name = self.next();
-				self.add(Node::Identifier { name })
-			}
-
-			TT::True => {
-				self.next();
-				self.add(Node::Bool { value: true })
-			}
-			TT::False => {
-				self.next();
-				self.add(Node::Bool { value: false })
-			}
-
-			TT::Integer => {
-				let value = self.next();
-				self.add(Node::Integer { value })
-			}
-			TT::Float => {
-				let value = self.next();
-				self.add(Node::Float { value })
-			}
-
-			TT::LParen => {
-				self.next();
-				let expr = self.expression();
-				if let Some(_) = self.catch(&[TT::RParen]) {
-					self.add(Node::Group { expr })
-				} else {
-					self.add(Node::Error)
-				}
-			}
-			TT::LBrace => {
-				self.next();
-				self.block(|kind| kind == TT::RBrace)
-			}
-
-			_ => {
-				self.report.error(format!("unexpected token: {:?}", kind));
-				self.next();
-				self.add(Node::Error)
-			}
-		}
-	}
-
-	fn type_expression(&mut self) -> NodeIndex {
-		self.type_primary()
-	}
-
-	fn type_primary(&mut self) -> NodeIndex {
-		let kind = self.peek().kind;
-
-		match kind {
-			TT::Identifier => {
-				let name = self.next();
-				self.add(Node::Identifier { name })
-			}
-			_ => {
-				self.report.error(format!("unexpected token: {:?}", kind));
-				self.next();
-				self.add(Node::Error)
-			}
-		}
-	}
-
-}
-
-pub fn parse<'a>(src: &'a str, tokens: &'a TokenStream<'a>) -> Result<Ast<'a>, Report> {
-	Parser::new(src, tokens).build()
-}
-
-
-#[cfg(test)]
-mod test {
-	use crate::{parse::*, token::tokenize};
-
-	#[test]
-	fn binary() {
-		let src = "1 + 1";
-		let tokens = tokenize(src).unwrap();
-		let ast = parse(src, &tokens).unwrap();
-		println!("{:#?}", ast);
-	}
-
-	#[test]
-	fn call() {
-		let src = "let f = fn (a) => a + a";
-		let tokens = tokenize(src).unwrap();
-		let ast = parse(src, &tokens).unwrap();
-		println!("{:#?}", ast);
-	}
-
-}
-
-
-
-
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{report::Report, token::{tokenize_with, Span, Token, TokenStream, TokenizeOptions, TT}};
+
+
+
+#[repr(transparent)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NodeIndex(pub u32);
+
+/// a side table attaching data to nodes, for analysis passes
+#[derive(Debug, Clone)]
+pub struct NodeMap<T> {
+	items: Vec<Option<T>>,
+}
+impl<T> NodeMap<T> {
+	pub fn new() -> Self {
+		Self { items: Vec::new() }
+	}
+
+	pub fn get(&self, index: &NodeIndex) -> Option<&T> {
+		self.items.get(index.0 as usize)?.as_ref()
+	}
+
+	pub fn get_mut(&mut self, index: &NodeIndex) -> Option<&mut T> {
+		self.items.get_mut(index.0 as usize)?.as_mut()
+	}
+
+	/// sets the value for a node, returning the one it replaced
+	pub fn insert(&mut self, index: &NodeIndex, value: T) -> Option<T> {
+		let at = index.0 as usize;
+		if at >= self.items.len() {
+			self.items.resize_with(at + 1, || None);
+		}
+		self.items[at].replace(value)
+	}
+
+	/// every node with a value, in index order
+	pub fn iter(&self) -> impl Iterator<Item = (NodeIndex, &T)> {
+		self.items.iter().enumerate()
+			.filter_map(|(i, v)| Some((NodeIndex(i as u32), v.as_ref()?)))
+	}
+}
+impl<T> Default for NodeMap<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[derive(Debug, Clone)]
+pub enum Node<'a> {
+	Error,
+	Module {
+		root: NodeIndex,
+	},
+	Block {
+		expr: Vec<NodeIndex>,
+	},
+	Identifier {
+		name: &'a Token,
+	},
+	Bool {
+		value: bool,
+	},
+	Nil,
+	Integer {
+		value: &'a Token,
+	},
+	Float {
+		value: &'a Token,
+	},
+	Fn {
+		/// set for `fn name (...) => ...`, which binds `name` in the
+		/// enclosing scope
+		name: Option<&'a Token>,
+		args: Vec<(&'a Token, Option<NodeIndex>)>,
+		/// whether the last argument is a rest parameter, as in `...rest`
+		variadic: bool,
+		ret: Option<NodeIndex>,
+		/// a [`Node::Where`] between the return type and `=>`
+		where_clause: Option<NodeIndex>,
+		expr: NodeIndex,
+	},
+	/// constraints on a function's generics, as in `where T: Display`. the
+	/// bounds are type expressions, but nothing checks them yet
+	Where {
+		op: &'a Token,
+		constraints: Vec<(&'a Token, NodeIndex)>,
+	},
+	Group {
+		expr: NodeIndex,
+	},
+	Record {
+		fields: Vec<(&'a Token, NodeIndex)>,
+	},
+	Tuple {
+		items: Vec<NodeIndex>,
+	},
+	Array {
+		items: Vec<NodeIndex>,
+	},
+	Ascribe {
+		expr: NodeIndex,
+		ty: NodeIndex,
+	},
+	Binary {
+		left: NodeIndex,
+		op: &'a Token,
+		right: NodeIndex,
+	},
+	Unary {
+		op: &'a Token,
+		right: NodeIndex,
+	},
+	Call {
+		op: &'a Token,
+		expr: NodeIndex,
+		args: Vec<NodeIndex>,
+	},
+	Index {
+		op: &'a Token,
+		expr: NodeIndex,
+		index: NodeIndex,
+	},
+	/// a record field, as in `a.b`, or a tuple item by position, as in `t.0`
+	Field {
+		expr: NodeIndex,
+		op: &'a Token,
+		name: &'a Token,
+	},
+	Slice {
+		op: &'a Token,
+		expr: NodeIndex,
+		range: NodeIndex,
+	},
+	/// a range with optional bounds, as in `a..b`, `..b`, or `a..`
+	Range {
+		start: Option<NodeIndex>,
+		op: &'a Token,
+		end: Option<NodeIndex>,
+	},
+	Try {
+		expr: NodeIndex,
+		op: &'a Token,
+	},
+	If {
+		op: &'a Token,
+		condition: NodeIndex,
+		then_branch: NodeIndex,
+		else_branch: Option<NodeIndex>,
+	},
+	IfLet {
+		op: &'a Token,
+		pattern: Pattern<'a>,
+		expr: NodeIndex,
+		then_branch: NodeIndex,
+		else_branch: Option<NodeIndex>,
+	},
+	Loop {
+		op: &'a Token,
+		label: Option<&'a Token>,
+		body: NodeIndex,
+	},
+	While {
+		op: &'a Token,
+		label: Option<&'a Token>,
+		condition: NodeIndex,
+		body: NodeIndex,
+	},
+	/// a loop that runs its body before checking the condition, as in
+	/// `repeat { ... } while cond`
+	RepeatWhile {
+		op: &'a Token,
+		label: Option<&'a Token>,
+		body: NodeIndex,
+		condition: NodeIndex,
+	},
+	Break {
+		op: &'a Token,
+		label: Option<&'a Token>,
+		/// what a `loop` evaluates to when this breaks out of it
+		value: Option<NodeIndex>,
+	},
+	Continue {
+		op: &'a Token,
+		label: Option<&'a Token>,
+	},
+	/// an optional type, as in `Int?`
+	Optional {
+		ty: NodeIndex,
+		op: &'a Token,
+	},
+	/// an array type, as in `[Int]`
+	ArrayType {
+		ty: NodeIndex,
+	},
+	Export {
+		op: &'a Token,
+		item: NodeIndex,
+	},
+	/// `#[name]` or `#[name(args)]` on the declaration after it. several
+	/// attributes nest, the first one outermost
+	Attribute {
+		op: &'a Token,
+		name: &'a Token,
+		args: Vec<&'a Token>,
+		item: NodeIndex,
+	},
+	/// `type Name = Type`
+	TypeAlias {
+		op: &'a Token,
+		name: &'a Token,
+		ty: NodeIndex,
+	},
+	/// `enum Name { A(Int, Bool), B }`, a type with a fixed set of variants.
+	/// each variant has the types of its payload, if it has one
+	Enum {
+		op: &'a Token,
+		name: &'a Token,
+		variants: Vec<(&'a Token, Vec<NodeIndex>)>,
+	},
+	Assign {
+		target: NodeIndex,
+		op: &'a Token,
+		value: NodeIndex,
+	},
+	/// `const NAME = expr`, whose value must be known before running
+	Const {
+		op: &'a Token,
+		name: &'a Token,
+		ty: Option<NodeIndex>,
+		expr: NodeIndex,
+	},
+	Let {
+		mutable: bool,
+		pattern: Pattern<'a>,
+		expr: NodeIndex,
+		annotation: Option<NodeIndex>,
+	},
+}
+
+/// the left hand side of a `let`
+#[derive(Debug, Clone)]
+pub enum Pattern<'a> {
+	Identifier {
+		name: &'a Token,
+	},
+	Tuple {
+		op: &'a Token,
+		items: Vec<Pattern<'a>>,
+	},
+}
+impl<'a> Pattern<'a> {
+	/// every name the pattern binds, in source order
+	pub fn names(&self) -> Vec<&'a Token> {
+		match self {
+			Pattern::Identifier { name } => vec![name],
+			Pattern::Tuple { items, .. } => items.iter().flat_map(|v| v.names()).collect(),
+		}
+	}
+
+	fn tokens(&self) -> Vec<&'a Token> {
+		match self {
+			Pattern::Identifier { name } => vec![name],
+			Pattern::Tuple { op, items } => {
+				let mut tokens = vec![*op];
+				tokens.extend(items.iter().flat_map(|v| v.tokens()));
+				tokens
+			}
+		}
+	}
+
+	fn names_mut(&mut self) -> Vec<&mut &'a Token> {
+		match self {
+			Pattern::Identifier { name } => vec![name],
+			Pattern::Tuple { items, .. } => items.iter_mut().flat_map(|v| v.names_mut()).collect(),
+		}
+	}
+
+
+	fn map_tokens<'b>(&self, f: &mut impl FnMut(&'a Token) -> &'b Token) -> Pattern<'b> {
+		match self {
+			Pattern::Identifier { name } => Pattern::Identifier { name: f(name) },
+			Pattern::Tuple { op, items } => Pattern::Tuple {
+				op: f(op),
+				items: items.iter().map(|v| v.map_tokens(f)).collect(),
+			},
+		}
+	}
+}
+
+impl<'a> Node<'a> {
+	/// the direct children of a node, in source order
+	pub fn children(&self) -> Vec<NodeIndex> {
+		match self {
+			Node::Error
+			| Node::Identifier { .. }
+			| Node::Bool { .. }
+			| Node::Nil
+			| Node::Integer { .. }
+			| Node::Float { .. }
+			| Node::Continue { .. } => vec![],
+
+			Node::Break { value, .. } => value.iter().cloned().collect(),
+
+			Node::Module { root } => vec![root.clone()],
+			Node::Block { expr } => expr.clone(),
+			Node::Fn { args, ret, where_clause, expr, .. } => {
+				let mut children = args.iter()
+					.filter_map(|(_, annotation)| annotation.clone())
+					.collect::<Vec<_>>();
+				children.extend(ret.clone());
+				children.extend(where_clause.clone());
+				children.push(expr.clone());
+				children
+			}
+			Node::Where { constraints, .. } => constraints.iter().map(|(_, v)| v.clone()).collect(),
+			Node::Group { expr } => vec![expr.clone()],
+			Node::Record { fields } => fields.iter().map(|(_, v)| v.clone()).collect(),
+			Node::Tuple { items }
+			| Node::Array { items } => items.clone(),
+			Node::Ascribe { expr, ty } => vec![expr.clone(), ty.clone()],
+			Node::Binary { left, right, .. } => vec![left.clone(), right.clone()],
+			Node::Unary { right, .. } => vec![right.clone()],
+			Node::Call { expr, args, .. } => {
+				let mut children = vec![expr.clone()];
+				children.extend(args.iter().cloned());
+				children
+			}
+			Node::Index { expr, index, .. } => vec![expr.clone(), index.clone()],
+			Node::Field { expr, .. } => vec![expr.clone()],
+			Node::Slice { expr, range, .. } => vec![expr.clone(), range.clone()],
+			Node::Range { start, end, .. } => start.iter().chain(end.iter()).cloned().collect(),
+			Node::Try { expr, .. } => vec![expr.clone()],
+			Node::If { condition, then_branch, else_branch, .. } => {
+				let mut children = vec![condition.clone(), then_branch.clone()];
+				children.extend(else_branch.clone());
+				children
+			}
+			Node::IfLet { expr, then_branch, else_branch, .. } => {
+				let mut children = vec![expr.clone(), then_branch.clone()];
+				children.extend(else_branch.clone());
+				children
+			}
+			Node::Loop { body, .. } => vec![body.clone()],
+			Node::While { condition, body, .. } => vec![condition.clone(), body.clone()],
+			Node::RepeatWhile { body, condition, .. } => vec![body.clone(), condition.clone()],
+			Node::Optional { ty, .. }
+			| Node::ArrayType { ty } => vec![ty.clone()],
+			Node::Export { item, .. }
+			| Node::Attribute { item, .. } => vec![item.clone()],
+			Node::TypeAlias { ty, .. } => vec![ty.clone()],
+			Node::Enum { variants, .. } => variants.iter().flat_map(|(_, payload)| payload.iter().cloned()).collect(),
+			Node::Assign { target, value, .. } => vec![target.clone(), value.clone()],
+			Node::Const { ty: annotation, expr, .. }
+			| Node::Let { expr, annotation, .. } => {
+				let mut children = annotation.iter().cloned().collect::<Vec<_>>();
+				children.push(expr.clone());
+				children
+			}
+		}
+	}
+
+	/// the tokens a node holds directly, not counting its children's
+	pub fn tokens(&self) -> Vec<&'a Token> {
+		match self {
+			Node::Error
+			| Node::Module { .. }
+			| Node::Block { .. }
+			| Node::Bool { .. }
+			| Node::Nil
+			| Node::Group { .. }
+			| Node::Tuple { .. }
+			| Node::Array { .. }
+			| Node::ArrayType { .. }
+			| Node::Ascribe { .. } => vec![],
+
+			Node::Identifier { name } => vec![name],
+			Node::Integer { value } | Node::Float { value } => vec![value],
+			Node::Fn { name, args, .. } => name.iter().copied()
+				.chain(args.iter().map(|(name, _)| *name))
+				.collect(),
+			Node::Record { fields } => fields.iter().map(|(name, _)| *name).collect(),
+			Node::Attribute { op, name, args, .. } => {
+				let mut tokens = vec![*op, *name];
+				tokens.extend(args);
+				tokens
+			}
+			Node::Field { op, name, .. } => vec![op, name],
+			Node::Enum { op, name, variants } => {
+				let mut tokens = vec![*op, *name];
+				tokens.extend(variants.iter().map(|(name, _)| *name));
+				tokens
+			}
+			Node::Where { op, constraints } => {
+				let mut tokens = vec![*op];
+				tokens.extend(constraints.iter().map(|(name, _)| *name));
+				tokens
+			}
+			Node::Binary { op, .. }
+			| Node::Unary { op, .. }
+			| Node::Call { op, .. }
+			| Node::Index { op, .. }
+			| Node::Slice { op, .. }
+			| Node::Range { op, .. }
+			| Node::Try { op, .. }
+			| Node::If { op, .. }
+			| Node::Optional { op, .. }
+			| Node::Export { op, .. }
+			| Node::Assign { op, .. } => vec![op],
+			Node::TypeAlias { op, name, .. }
+			| Node::Const { op, name, .. } => vec![op, name],
+			Node::IfLet { op, pattern, .. } => {
+				let mut tokens = vec![*op];
+				tokens.extend(pattern.tokens());
+				tokens
+			}
+			Node::Loop { op, label, .. }
+			| Node::While { op, label, .. }
+			| Node::RepeatWhile { op, label, .. }
+			| Node::Break { op, label, .. }
+			| Node::Continue { op, label } => {
+				let mut tokens = vec![*op];
+				tokens.extend(*label);
+				tokens
+			}
+			Node::Let { pattern, .. } => pattern.tokens(),
+		}
+	}
+
+	/// a copy of the node with each of its tokens passed through `f`, in
+	/// the order [`Node::tokens`] gives them
+	pub fn map_tokens<'b>(&self, f: &mut impl FnMut(&'a Token) -> &'b Token) -> Node<'b> {
+		match self {
+			Node::Error => Node::Error,
+			Node::Module { root } => Node::Module { root: root.clone() },
+			Node::Block { expr } => Node::Block { expr: expr.clone() },
+			Node::Identifier { name } => Node::Identifier { name: f(name) },
+			Node::Bool { value } => Node::Bool { value: *value },
+			Node::Nil => Node::Nil,
+			Node::Integer { value } => Node::Integer { value: f(value) },
+			Node::Float { value } => Node::Float { value: f(value) },
+			Node::Fn { name, args, variadic, ret, where_clause, expr } => Node::Fn {
+				name: name.map(&mut *f),
+				args: args.iter().map(|(name, ty)| (f(name), ty.clone())).collect(),
+				variadic: *variadic,
+				ret: ret.clone(),
+				where_clause: where_clause.clone(),
+				expr: expr.clone(),
+			},
+			Node::Where { op, constraints } => Node::Where {
+				op: f(op),
+				constraints: constraints.iter().map(|(name, ty)| (f(name), ty.clone())).collect(),
+			},
+			Node::Group { expr } => Node::Group { expr: expr.clone() },
+			Node::Record { fields } => Node::Record {
+				fields: fields.iter().map(|(name, value)| (f(name), value.clone())).collect(),
+			},
+			Node::Tuple { items } => Node::Tuple { items: items.clone() },
+			Node::Array { items } => Node::Array { items: items.clone() },
+			Node::Ascribe { expr, ty } => Node::Ascribe { expr: expr.clone(), ty: ty.clone() },
+			Node::Binary { left, op, right } => Node::Binary { left: left.clone(), op: f(op), right: right.clone() },
+			Node::Unary { op, right } => Node::Unary { op: f(op), right: right.clone() },
+			Node::Call { op, expr, args } => Node::Call { op: f(op), expr: expr.clone(), args: args.clone() },
+			Node::Index { op, expr, index } => Node::Index { op: f(op), expr: expr.clone(), index: index.clone() },
+			Node::Field { expr, op, name } => {
+				let op = f(op);
+				Node::Field { expr: expr.clone(), op, name: f(name) }
+			}
+			Node::Slice { op, expr, range } => Node::Slice { op: f(op), expr: expr.clone(), range: range.clone() },
+			Node::Range { start, op, end } => Node::Range { start: start.clone(), op: f(op), end: end.clone() },
+			Node::Try { expr, op } => Node::Try { expr: expr.clone(), op: f(op) },
+			Node::If { op, condition, then_branch, else_branch } => Node::If {
+				op: f(op),
+				condition: condition.clone(),
+				then_branch: then_branch.clone(),
+				else_branch: else_branch.clone(),
+			},
+			Node::IfLet { op, pattern, expr, then_branch, else_branch } => Node::IfLet {
+				op: f(op),
+				pattern: pattern.map_tokens(f),
+				expr: expr.clone(),
+				then_branch: then_branch.clone(),
+				else_branch: else_branch.clone(),
+			},
+			Node::Loop { op, label, body } => Node::Loop { op: f(op), label: label.map(&mut *f), body: body.clone() },
+			Node::While { op, label, condition, body } => Node::While {
+				op: f(op),
+				label: label.map(&mut *f),
+				condition: condition.clone(),
+				body: body.clone(),
+			},
+			Node::RepeatWhile { op, label, body, condition } => Node::RepeatWhile {
+				op: f(op),
+				label: label.map(&mut *f),
+				body: body.clone(),
+				condition: condition.clone(),
+			},
+			Node::Break { op, label, value } => Node::Break { op: f(op), label: label.map(&mut *f), value: value.clone() },
+			Node::Continue { op, label } => Node::Continue { op: f(op), label: label.map(&mut *f) },
+			Node::Optional { ty, op } => Node::Optional { ty: ty.clone(), op: f(op) },
+			Node::ArrayType { ty } => Node::ArrayType { ty: ty.clone() },
+			Node::Export { op, item } => Node::Export { op: f(op), item: item.clone() },
+			Node::Attribute { op, name, args, item } => {
+				let (op, name) = (f(op), f(name));
+				Node::Attribute { op, name, args: args.iter().map(|v| f(v)).collect(), item: item.clone() }
+			}
+			Node::TypeAlias { op, name, ty } => {
+				let op = f(op);
+				Node::TypeAlias { op, name: f(name), ty: ty.clone() }
+			}
+			Node::Enum { op, name, variants } => {
+				let (op, name) = (f(op), f(name));
+				Node::Enum {
+					op,
+					name,
+					variants: variants.iter().map(|(name, payload)| (f(name), payload.clone())).collect(),
+				}
+			}
+			Node::Assign { target, op, value } => Node::Assign { target: target.clone(), op: f(op), value: value.clone() },
+			Node::Const { op, name, ty, expr } => {
+				let op = f(op);
+				Node::Const { op, name: f(name), ty: ty.clone(), expr: expr.clone() }
+			}
+			Node::Let { mutable, pattern, expr, annotation } => Node::Let {
+				mutable: *mutable,
+				pattern: pattern.map_tokens(f),
+				expr: expr.clone(),
+				annotation: annotation.clone(),
+			},
+		}
+	}
+
+	pub fn kind(&self) -> NodeKind {
+		match self {
+			Node::Error => NodeKind::Error,
+			Node::Module { .. } => NodeKind::Module,
+			Node::Block { .. } => NodeKind::Block,
+			Node::Identifier { .. } => NodeKind::Identifier,
+			Node::Bool { .. } => NodeKind::Bool,
+			Node::Integer { .. } => NodeKind::Integer,
+			Node::Float { .. } => NodeKind::Float,
+			Node::Fn { .. } => NodeKind::Fn,
+			Node::Where { .. } => NodeKind::Where,
+			Node::Group { .. } => NodeKind::Group,
+			Node::Record { .. } => NodeKind::Record,
+			Node::Tuple { .. } => NodeKind::Tuple,
+			Node::Array { .. } => NodeKind::Array,
+			Node::Ascribe { .. } => NodeKind::Ascribe,
+			Node::Binary { .. } => NodeKind::Binary,
+			Node::Unary { .. } => NodeKind::Unary,
+			Node::Call { .. } => NodeKind::Call,
+			Node::Index { .. } => NodeKind::Index,
+			Node::Field { .. } => NodeKind::Field,
+			Node::Slice { .. } => NodeKind::Slice,
+			Node::Range { .. } => NodeKind::Range,
+			Node::Try { .. } => NodeKind::Try,
+			Node::If { .. } => NodeKind::If,
+			Node::IfLet { .. } => NodeKind::IfLet,
+			Node::Loop { .. } => NodeKind::Loop,
+			Node::While { .. } => NodeKind::While,
+			Node::RepeatWhile { .. } => NodeKind::RepeatWhile,
+			Node::Break { .. } => NodeKind::Break,
+			Node::Continue { .. } => NodeKind::Continue,
+			Node::Nil => NodeKind::Nil,
+			Node::Optional { .. } => NodeKind::Optional,
+			Node::ArrayType { .. } => NodeKind::ArrayType,
+			Node::Export { .. } => NodeKind::Export,
+			Node::Attribute { .. } => NodeKind::Attribute,
+			Node::TypeAlias { .. } => NodeKind::TypeAlias,
+			Node::Enum { .. } => NodeKind::Enum,
+			Node::Const { .. } => NodeKind::Const,
+			Node::Assign { .. } => NodeKind::Assign,
+			Node::Let { .. } => NodeKind::Let,
+		}
+	}
+}
+
+/// the variant of a [`Node`], without its contents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+	Error,
+	Module,
+	Block,
+	Identifier,
+	Bool,
+	Nil,
+	Integer,
+	Float,
+	Fn,
+	Where,
+	Group,
+	Record,
+	Tuple,
+	Array,
+	Ascribe,
+	Binary,
+	Unary,
+	Call,
+	Index,
+	Field,
+	Slice,
+	Range,
+	Try,
+	If,
+	IfLet,
+	Loop,
+	While,
+	RepeatWhile,
+	Break,
+	Continue,
+	Optional,
+	ArrayType,
+	Export,
+	Attribute,
+	TypeAlias,
+	Enum,
+	Assign,
+	Const,
+	Let,
+}
+
+#[derive(Debug)]
+pub struct Ast<'a> {
+	pub tokens: &'a TokenStream<'a>,
+	/// nodes in post-order: every node comes after all of its children, so
+	/// a single pass over `nodes` visits the tree bottom-up
+	pub nodes: Vec<Node<'a>>,
+	/// the span of each node, parallel to `nodes`
+	pub spans: Vec<Span>,
+	pub root: NodeIndex,
+}
+impl<'a> Ast<'a> {
+	pub fn get(&self, node: &NodeIndex) -> &Node<'a> {
+		&self.nodes[node.0 as usize]
+	}
+
+	/// the direct children of a node, in source order
+	pub fn children(&self, index: &NodeIndex) -> Vec<NodeIndex> {
+		self.get(index).children()
+	}
+
+	/// the source covered by a node and all of its children
+	pub fn span(&self, index: &NodeIndex) -> Span {
+		self.spans[index.0 as usize]
+	}
+
+	/// the smallest node whose span holds the byte at `offset`. nodes are
+	/// stored children first, so of several nodes with the same span the
+	/// innermost one wins
+	pub fn node_at_offset(&self, offset: u32) -> Option<NodeIndex> {
+		self.spans.iter().enumerate()
+			.filter(|(_, span)| span.start <= offset && offset < span.end)
+			.min_by_key(|(_, span)| span.end - span.start)
+			.map(|(i, _)| NodeIndex(i as u32))
+	}
+
+	/// a compact s-expression rendering of the tree, like
+	/// `(binary + (int 1) (int 1))`, with one line per top level statement.
+	/// unlike `Debug` it leaves out spans and indices, so it's stable enough
+	/// to compare against in tests
+	pub fn dump(&self) -> String {
+		let mut out = String::new();
+		let statements = match (self.get(&self.root), self.nodes.len()) {
+			(_, 0) => vec![],
+			(Node::Module { root }, _) => match self.get(root) {
+				Node::Block { expr } => expr.clone(),
+				_ => vec![root.clone()],
+			},
+			_ => vec![self.root.clone()],
+		};
+		for (i, v) in statements.iter().enumerate() {
+			if i > 0 {
+				out.push('\n');
+			}
+			self.dump_node(v, &mut out);
+		}
+		out
+	}
+
+	fn dump_node(&self, index: &NodeIndex, out: &mut String) {
+		let src: &str = &self.tokens.src;
+		let child = |out: &mut String, child: &NodeIndex| {
+			out.push(' ');
+			self.dump_node(child, out);
+		};
+		let children = |out: &mut String, children: &[NodeIndex]| {
+			for v in children {
+				child(out, v);
+			}
+		};
+		// a missing optional child is `_`, so `..1` and `1..` differ
+		let maybe = |out: &mut String, v: &Option<NodeIndex>| match v {
+			Some(v) => child(out, v),
+			None => out.push_str(" _"),
+		};
+
+		out.push('(');
+		match self.get(index) {
+			Node::Identifier { name } => {
+				out.push_str("ident ");
+				out.push_str(name.get(src));
+			}
+			Node::Bool { value } => out.push_str(if *value { "bool true" } else { "bool false" }),
+			Node::Integer { value } => {
+				out.push_str("int ");
+				out.push_str(value.get(src));
+			}
+			Node::Float { value } => {
+				out.push_str("float ");
+				out.push_str(value.get(src));
+			}
+			Node::Binary { left, op, right } => {
+				out.push_str("binary ");
+				out.push_str(op.get(src));
+				child(out, left);
+				child(out, right);
+			}
+			Node::Unary { op, right } => {
+				out.push_str("unary ");
+				out.push_str(op.get(src));
+				child(out, right);
+			}
+			Node::Enum { name, variants, .. } => {
+				out.push_str("enum ");
+				out.push_str(name.get(src));
+				for (name, payload) in variants {
+					out.push_str(" (");
+					out.push_str(name.get(src));
+					for v in payload {
+						child(out, v);
+					}
+					out.push(')');
+				}
+			}
+			Node::Field { expr, name, .. } => {
+				out.push_str("field");
+				child(out, expr);
+				out.push(' ');
+				out.push_str(name.get(src));
+			}
+			Node::Record { fields } => {
+				out.push_str("record");
+				for (name, value) in fields {
+					out.push_str(" (");
+					out.push_str(name.get(src));
+					child(out, value);
+					out.push(')');
+				}
+			}
+			Node::Fn { name, args, variadic, ret, where_clause, expr } => {
+				out.push_str("fn ");
+				if let Some(name) = name {
+					out.push_str(name.get(src));
+					out.push(' ');
+				}
+				out.push('(');
+				for (i, (name, annotation)) in args.iter().enumerate() {
+					if i > 0 {
+						out.push(' ');
+					}
+					let rest = if *variadic && i + 1 == args.len() { "..." } else { "" };
+					match annotation {
+						Some(annotation) => {
+							out.push('(');
+							out.push_str(rest);
+							out.push_str(name.get(src));
+							child(out, annotation);
+							out.push(')');
+						}
+						None => {
+							out.push_str(rest);
+							out.push_str(name.get(src));
+						}
+					}
+				}
+				out.push(')');
+				maybe(out, ret);
+				if let Some(where_clause) = where_clause {
+					child(out, where_clause);
+				}
+				child(out, expr);
+			}
+			Node::Where { constraints, .. } => {
+				out.push_str("where");
+				for (name, bound) in constraints {
+					out.push_str(" (");
+					out.push_str(name.get(src));
+					child(out, bound);
+					out.push(')');
+				}
+			}
+			Node::Range { start, end, .. } => {
+				out.push_str("range");
+				maybe(out, start);
+				maybe(out, end);
+			}
+			Node::IfLet { pattern, expr, then_branch, else_branch, .. } => {
+				out.push_str("if_let ");
+				dump_pattern(src, pattern, out);
+				child(out, expr);
+				child(out, then_branch);
+				children(out, else_branch.as_slice());
+			}
+			Node::Let { mutable, pattern, expr, annotation } => {
+				out.push_str(if *mutable { "let mut " } else { "let " });
+				dump_pattern(src, pattern, out);
+				children(out, annotation.as_slice());
+				child(out, expr);
+			}
+			// everything else is its kind, any names, labels, or literals, and
+			// then its children
+			node => {
+				let kind = format!("{:?}", node.kind());
+				for (i, c) in kind.chars().enumerate() {
+					if c.is_uppercase() && i > 0 {
+						out.push('_');
+					}
+					out.push(c.to_ascii_lowercase());
+				}
+				for token in node.tokens() {
+					if matches!(token.kind, TT::Identifier | TT::Label) || token.kind.is_literal() {
+						out.push(' ');
+						out.push_str(token.get(src));
+					}
+				}
+				children(out, &node.children());
+			}
+		}
+		out.push(')');
+	}
+
+	/// every identifier node with its name and span, in source order. names
+	/// bound by a pattern, like the `x` in `let x = 1`, aren't nodes and
+	/// are left out
+	pub fn collect_identifiers(&self) -> Vec<(NodeIndex, &'a str, Span)> {
+		let src: &'a str = &self.tokens.src;
+		self.nodes.iter().enumerate()
+			.filter_map(|(i, v)| match v {
+				Node::Identifier { name } => Some((NodeIndex(i as u32), name.get(src), self.spans[i])),
+				_ => None,
+			})
+			.collect()
+	}
+
+	/// the names used in a subtree without being bound in it, leaving out
+	/// those already in `bound`. names in type position, like the `Int` in
+	/// `x: Int`, aren't values and never count
+	pub fn free_vars(&self, index: &NodeIndex, bound: &HashSet<&str>) -> HashSet<&'a str> {
+		let mut out = HashSet::new();
+		self.free(index, bound, &mut vec![], &mut out);
+		out
+	}
+
+	// `scope` holds the names bound on the way down, innermost last. a node
+	// that opens a scope truncates it back when it's done
+	fn free(&self, index: &NodeIndex, bound: &HashSet<&str>, scope: &mut Vec<&'a str>, out: &mut HashSet<&'a str>) {
+		let src: &'a str = &self.tokens.src;
+		match self.get(index) {
+			Node::Identifier { name } => {
+				let name = name.get(src);
+				if !bound.contains(name) && !scope.contains(&name) {
+					out.insert(name);
+				}
+			}
+			Node::Block { expr } => {
+				let mark = scope.len();
+				// like in the binder, named functions can be used before them
+				for v in expr {
+					let mut node = self.get(v);
+					while let Node::Export { item, .. } | Node::Attribute { item, .. } = node {
+						node = self.get(item);
+					}
+					if let Node::Fn { name: Some(name), .. } = node {
+						scope.push(name.get(src));
+					}
+				}
+				for v in expr {
+					self.free(v, bound, scope, out);
+				}
+				scope.truncate(mark);
+			}
+			// the names stay bound until the enclosing block ends
+			Node::Let { pattern, expr, .. } => {
+				self.free(expr, bound, scope, out);
+				scope.extend(pattern.names().into_iter().map(|v| v.get(src)));
+			}
+			Node::Const { name, expr, .. } => {
+				self.free(expr, bound, scope, out);
+				scope.push(name.get(src));
+			}
+			// the variants are constructors, so they're values
+			Node::Enum { variants, .. } => {
+				scope.extend(variants.iter().map(|(name, _)| name.get(src)));
+			}
+			Node::Fn { name, args, expr, .. } => {
+				let mark = scope.len();
+				scope.extend(name.iter().chain(args.iter().map(|(name, _)| name)).map(|v| v.get(src)));
+				self.free(expr, bound, scope, out);
+				scope.truncate(mark);
+			}
+			Node::IfLet { pattern, expr, then_branch, else_branch, .. } => {
+				self.free(expr, bound, scope, out);
+				let mark = scope.len();
+				scope.extend(pattern.names().into_iter().map(|v| v.get(src)));
+				self.free(then_branch, bound, scope, out);
+				scope.truncate(mark);
+				if let Some(else_branch) = else_branch {
+					self.free(else_branch, bound, scope, out);
+				}
+			}
+			Node::Ascribe { expr, .. } => self.free(expr, bound, scope, out),
+			Node::TypeAlias { .. } | Node::Where { .. } | Node::Optional { .. } | Node::ArrayType { .. } => (),
+			node => {
+				for v in node.children() {
+					self.free(&v, bound, scope, out);
+				}
+			}
+		}
+	}
+
+	/// the whole tree drawn with box characters, one node per line, as its
+	/// kind and the text of its tokens. meant for reading in a terminal, so
+	/// unlike [`Ast::dump`] its layout may change
+	pub fn pretty_tree(&self) -> String {
+		let mut out = String::new();
+		if !self.nodes.is_empty() {
+			self.pretty_node(&self.root, "", "", &mut out);
+		}
+		out
+	}
+
+	// `first` is drawn before the node itself, `rest` before its children
+	fn pretty_node(&self, index: &NodeIndex, first: &str, rest: &str, out: &mut String) {
+		let src: &str = &self.tokens.src;
+		let node = self.get(index);
+		out.push_str(first);
+		out.push_str(&format!("{:?}", node.kind()));
+		// brackets say nothing the tree doesn't already
+		let brackets = [TT::LParen, TT::RParen, TT::LBracket, TT::RBracket, TT::Hash];
+		for token in node.tokens().into_iter().filter(|v| !brackets.contains(&v.kind)) {
+			out.push(' ');
+			out.push_str(token.get(src));
+		}
+		out.push('\n');
+
+		let children = node.children();
+		for (i, child) in children.iter().enumerate() {
+			if i + 1 == children.len() {
+				self.pretty_node(child, &format!("{}└─ ", rest), &format!("{}   ", rest), out);
+			} else {
+				self.pretty_node(child, &format!("{}├─ ", rest), &format!("{}│  ", rest), out);
+			}
+		}
+	}
+}
+
+
+fn dump_pattern(src: &str, pattern: &Pattern, out: &mut String) {
+	match pattern {
+		Pattern::Identifier { name } => out.push_str(name.get(src)),
+		Pattern::Tuple { items, .. } => {
+			out.push('(');
+			for (i, v) in items.iter().enumerate() {
+				if i > 0 {
+					out.push(' ');
+				}
+				dump_pattern(src, v, out);
+			}
+			out.push(')');
+		}
+	}
+}
+
+
+/// how operators of the same precedence group, as in `1 - 2 - 3`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+	/// `(1 - 2) - 3`
+	Left,
+	/// `1 - (2 - 3)`
+	Right,
+	/// can't be chained at all, like `a < b < c`
+	None,
+}
+
+/// the precedence and associativity of each binary operator. a higher
+/// precedence binds tighter
+#[derive(Debug, Clone)]
+pub struct OperatorTable {
+	operators: Vec<(TT, u8, Associativity)>,
+}
+impl Default for OperatorTable {
+	fn default() -> Self {
+		let mut table = Self { operators: Vec::new() };
+		for op in [TT::EqualEqual, TT::BangEqual, TT::Lesser, TT::LesserEqual, TT::Greater, TT::GreaterEqual] {
+			table.set(op, 1, Associativity::None);
+		}
+		for op in [TT::Plus, TT::Minus] {
+			table.set(op, 2, Associativity::Left);
+		}
+		for op in [TT::Star, TT::Slash, TT::Percent] {
+			table.set(op, 3, Associativity::Left);
+		}
+		table
+	}
+}
+impl OperatorTable {
+	/// sets how `op` parses, replacing what it had before.
+	///
+	/// panics if `op` isn't a binary operator
+	pub fn set(&mut self, op: TT, precedence: u8, associativity: Associativity) {
+		assert!(op.is_binary_op(), "{:?} isn't a binary operator", op);
+		self.operators.retain(|(v, ..)| *v != op);
+		self.operators.push((op, precedence, associativity));
+	}
+
+	pub fn get(&self, op: TT) -> Option<(u8, Associativity)> {
+		self.operators.iter()
+			.find(|(v, ..)| *v == op)
+			.map(|(_, precedence, associativity)| (*precedence, *associativity))
+	}
+}
+
+// tokens that can begin an expression, besides identifiers and literals
+const EXPRESSION_STARTS: &[TT] = &[
+	TT::LParen, TT::LBracket, TT::LBrace, TT::Minus, TT::Bang,
+	TT::Fn, TT::If, TT::Loop, TT::While, TT::Repeat, TT::Break, TT::Continue,
+];
+
+// the parser's position and diagnostics, saved before a speculative parse so
+// it can be undone with `Parser::rollback`
+struct Checkpoint<'a> {
+	current: usize,
+	previous: Option<&'a Token>,
+	nodes: usize,
+	report: Report,
+}
+
+struct Parser<'a> {
+	src: &'a str,
+	tokens: &'a TokenStream<'a>,
+	nodes: Vec<Node<'a>>,
+	spans: Vec<Span>,
+	// the index of the next token to read, which is never trivia
+	current: usize,
+	// the last token consumed
+	previous: Option<&'a Token>,
+	// the most nodes a tree can have while indices still fit in a u32
+	max_nodes: usize,
+	operators: OperatorTable,
+	// set while parsing a condition, like the one after `if`, where a `{`
+	// after a call starts the body rather than a trailing block
+	condition: bool,
+	report: Report,
+}
+impl<'a> Parser<'a> {
+	fn new(src: &'a str, tokens: &'a TokenStream) -> Self {
+		Self {
+			src,
+			tokens,
+			nodes: Vec::new(),
+			spans: Vec::new(),
+			max_nodes: u32::MAX as usize,
+			operators: OperatorTable::default(),
+			condition: false,
+			previous: None,
+			current: Self::skip_trivia(tokens, 0),
+			report: Report::new(),
+		}
+	}
+
+	fn checkpoint(&self) -> Checkpoint<'a> {
+		Checkpoint {
+			current: self.current,
+			previous: self.previous,
+			nodes: self.nodes.len(),
+			report: self.report.clone(),
+		}
+	}
+
+	// goes back to a checkpoint, dropping every node and diagnostic added
+	// since. nodes only refer to earlier ones, so none that are left can
+	// point at a dropped one
+	fn rollback(&mut self, checkpoint: Checkpoint<'a>) {
+		self.current = checkpoint.current;
+		self.previous = checkpoint.previous;
+		self.nodes.truncate(checkpoint.nodes);
+		self.spans.truncate(checkpoint.nodes);
+		self.report = checkpoint.report;
+	}
+
+	// trivia like comments is kept for tools, but never parsed
+	fn skip_trivia(tokens: &TokenStream, from: usize) -> usize {
+		tokens.tokens[from..].iter()
+			.position(|v| !v.kind.is_trivia())
+			.map_or(tokens.tokens.len(), |v| from + v)
+	}
+
+	// a node's span covers its own tokens, its children, and the token just
+	// consumed, since a node is only added once all of its tokens are. this
+	// catches closing delimiters, which nodes don't keep
+	fn add(&mut self, value: Node<'a>) -> NodeIndex {
+		let spans = value.tokens().into_iter().map(|v| v.span())
+			.chain(value.children().into_iter().map(|v| self.spans[v.0 as usize]))
+			.filter(|v| v.start < v.end)
+			.chain(self.previous.map(|v| v.span()));
+		let span = spans.reduce(|a, b| Span::new(a.start.min(b.start), a.end.max(b.end)))
+			.unwrap_or(Span::new(0, 0));
+		if self.nodes.len() == self.max_nodes {
+			self.report.error(format!("too many nodes: the maximum is {}", self.max_nodes));
+		}
+		// past the limit the tree is never returned, so the index only needs
+		// to stay in bounds until parsing ends
+		let index = self.nodes.len().min(self.max_nodes.saturating_sub(1));
+		self.spans.push(span);
+		self.nodes.push(value);
+		NodeIndex(index as u32)
+	}
+
+	// stretches a node's span back to a leading token it doesn't keep, like
+	// the `let` of a binding or the `(` of a group
+	fn widen(&mut self, index: NodeIndex, start: &Token) -> NodeIndex {
+		let span = &mut self.spans[index.0 as usize];
+		span.start = span.start.min(start.span().start);
+		index
+	}
+
+	// the TT::Eof at the end is never consumed: advancing onto it again is a
+	// no-op that returns it, so error recovery can't run off the end. its
+	// span is empty, so it doesn't count as the previous token either
+	fn next(&mut self) -> &'a Token {
+		let token = self.peek();
+		if token.kind != TT::Eof {
+			self.current = Self::skip_trivia(self.tokens, self.current + 1);
+			self.previous = Some(token);
+		}
+		token
+	}
+	fn peek(&self) -> &'a Token {
+		// a token stream always ends in TT::Eof
+		self.peek_nth(0).or(self.tokens.tokens.last()).unwrap()
+	}
+	// the token `n` places after the next one, so `peek_nth(0)` is `peek()`
+	fn peek_nth(&self, n: usize) -> Option<&'a Token> {
+		self.tokens.tokens.get(self.current..)?.iter()
+			.filter(|v| !v.kind.is_trivia())
+			.nth(n)
+	}
+
+	fn catch(&mut self, check: &[TT]) -> Option<&'a Token> {
+		let kind = self.peek_nth(0)?.kind;
+		if check.contains(&kind) {
+			Some(self.next())
+		} else {
+			None
+		}
+	}
+	fn catch_one(&mut self, kind: TT) -> Option<&'a Token> {
+		if self.peek_nth(0)?.kind == kind {
+			Some(self.next())
+		} else {
+			None
+		}
+	}
+
+	fn describe(&self, token: &Token) -> String {
+		match token.kind {
+			TT::Eof => "end of input".to_string(),
+			_ => format!("'{}'", token.get(self.src)),
+		}
+	}
+
+	/// consumes a token of `kind`, or reports what was found in its place
+	fn expect(&mut self, kind: TT, what: &str) -> Option<&'a Token> {
+		let token = self.catch_one(kind);
+		if token.is_none() {
+			let found = self.peek();
+			self.report.error_at(found.span(), format!("expected {}, found {}", what, self.describe(found)));
+		}
+		token
+	}
+
+	// like `expect`, for the delimiter closing `open`. running out of input
+	// first is reported at `open`, since that's the one left unclosed
+	fn close(&mut self, open: &Token, kind: TT, what: &str) -> Option<&'a Token> {
+		if self.peek().kind == TT::Eof {
+			self.report.error_at(open.span(), format!("unclosed '{}' started here", open.get(self.src)));
+			return None;
+		}
+		self.expect(kind, what)
+	}
+
+	fn build(mut self) -> Result<Ast<'a>, Report> {
+		let root = self.module();
+		self.report.into_result(Ast { tokens: self.tokens, nodes: self.nodes, spans: self.spans, root })
+	}
+
+	fn module(&mut self) -> NodeIndex {
+		let root = self.block(None);
+		self.add(Node::Module { root })
+	}
+
+	// parses statements up to and including the '}' closing `open`, or to
+	// the end of input without one. the end of input is never consumed, and
+	// ends any block with a missing '}'
+	fn block(&mut self, open: Option<&'a Token>) -> NodeIndex {
+		let end = if open.is_some() { TT::RBrace } else { TT::Eof };
+		let mut expr = Vec::new();
+
+		while let Some(c) = self.peek_nth(0) {
+			if c.kind == TT::Eof {
+				if let Some(open) = open {
+					self.close(open, end, "'}'");
+				}
+				break;
+			} else if c.kind == end {
+				self.next();
+				break;
+			}
+			expr.push(self.statement());
+			self.catch_one(TT::SemiColon);
+		}
+
+		self.add(Node::Block { expr })
+	}
+
+	fn statement(&mut self) -> NodeIndex {
+		if let Some(op) = self.catch_one(TT::Export) {
+			let found = self.peek();
+			if !matches!(found.kind, TT::Let | TT::Mut | TT::Fn) {
+				self.report.error_at(
+					found.span(),
+					format!("expected 'let', 'mut', or 'fn' after 'export', found {}", self.describe(found)),
+				);
+			}
+			let item = self.statement();
+			self.add(Node::Export { op, item })
+		} else if let Some(op) = self.catch_one(TT::Hash) {
+			self.attribute(op)
+		} else if let Some(op) = self.catch_one(TT::Const) {
+			let Some(name) = self.expect(TT::Identifier, "identifier") else {
+				return self.add(Node::Error);
+			};
+			let ty = self.catch_one(TT::Colon).map(|_| self.type_expression());
+			self.expect(TT::Equal, "'='");
+			let expr = self.expression();
+			self.add(Node::Const { op, name, ty, expr })
+		} else if let Some(op) = self.catch_one(TT::Type) {
+			let Some(name) = self.expect(TT::Identifier, "type name") else {
+				return self.add(Node::Error);
+			};
+			self.expect(TT::Equal, "'='");
+			let ty = self.type_expression();
+			self.add(Node::TypeAlias { op, name, ty })
+		} else if let Some(op) = self.catch_one(TT::Enum) {
+			let Some(name) = self.expect(TT::Identifier, "enum name") else {
+				return self.add(Node::Error);
+			};
+			self.expect(TT::LBrace, "'{'");
+			let mut variants = vec![];
+			while let Some(variant) = self.catch_one(TT::Identifier) {
+				let mut payload = vec![];
+				if self.catch_one(TT::LParen).is_some() {
+					while self.peek().kind != TT::RParen {
+						payload.push(self.type_expression());
+						if self.catch_one(TT::Comma).is_none() {
+							break;
+						}
+					}
+					self.expect(TT::RParen, "')' after the payload of a variant");
+				}
+				variants.push((variant, payload));
+				if self.catch_one(TT::Comma).is_none() {
+					break;
+				}
+			}
+			self.expect(TT::RBrace, "'}' after the variants of an enum");
+			self.add(Node::Enum { op, name, variants })
+		} else if let Some(op) = self.catch(&[TT::Let, TT::Mut]) {
+			let Some(pattern) = self.pattern() else {
+				return self.add(Node::Error);
+			};
+
+			let annotation =
+				if self.catch_one(TT::Colon).is_some() {
+					Some(self.type_expression())
+				} else {
+					None
+				};
+			
+			self.expect(TT::Equal, "'='");
+			
+			let expr = self.expression();
+			
+			let index = self.add(Node::Let { mutable: op.kind == TT::Mut, pattern, annotation, expr });
+			self.widen(index, op)
+		} else {
+			self.expression()
+		}
+	}
+
+	// the rest of `#[name(args)]` after the `#`, and the declaration it's on.
+	// arguments are kept as plain tokens, since nothing reads them yet
+	fn attribute(&mut self, op: &'a Token) -> NodeIndex {
+		self.expect(TT::LBracket, "'['");
+		let Some(name) = self.expect(TT::Identifier, "attribute name") else {
+			return self.add(Node::Error);
+		};
+		let mut args = vec![];
+		if self.catch_one(TT::LParen).is_some() {
+			while self.peek().kind != TT::RParen {
+				let found = self.peek();
+				if found.kind != TT::Identifier && !found.kind.is_literal() {
+					self.report.error_at(
+						found.span(),
+						format!("expected attribute argument, found {}", self.describe(found)),
+					);
+					break;
+				}
+				args.push(self.next());
+				if self.catch_one(TT::Comma).is_none() {
+					break;
+				}
+			}
+			self.expect(TT::RParen, "')'");
+		}
+		self.expect(TT::RBracket, "']'");
+
+		let found = self.peek();
+		if !matches!(found.kind, TT::Let | TT::Mut | TT::Fn | TT::Export | TT::Hash) {
+			self.report.error_at(
+				found.span(),
+				format!("expected a declaration after an attribute, found {}", self.describe(found)),
+			);
+		}
+		let item = self.statement();
+		let index = self.add(Node::Attribute { op, name, args, item });
+		self.widen(index, op)
+	}
+
+	fn pattern(&mut self) -> Option<Pattern<'a>> {
+		let Some(op) = self.catch_one(TT::LParen) else {
+			let name = self.expect(TT::Identifier, "identifier")?;
+			return Some(Pattern::Identifier { name });
+		};
+
+		let mut items = Vec::new();
+		while self.catch_one(TT::RParen).is_none() {
+			items.push(self.pattern()?);
+			if self.catch_one(TT::Comma).is_none() {
+				self.expect(TT::RParen, "',' or ')'")?;
+				break;
+			}
+		}
+		Some(Pattern::Tuple { op, items })
+	}
+
+	fn expression(&mut self) -> NodeIndex {
+		self.assignment()
+	}
+
+	// assignment is right associative, so `a = b = 1` is `a = (b = 1)`
+	fn assignment(&mut self) -> NodeIndex {
+		let target = self.function();
+		let Some(op) = self.catch_one(TT::Equal) else {
+			return target;
+		};
+		if !matches!(self.nodes[target.0 as usize], Node::Identifier { .. }) {
+			self.report.error_at(op.span(), "invalid assignment target".to_string());
+		}
+		let value = self.assignment();
+		self.add(Node::Assign { target, op, value })
+	}
+
+	fn function(&mut self) -> NodeIndex {
+		if let Some(keyword) = self.catch_one(TT::Fn) {
+			let name = self.catch_one(TT::Identifier);
+			self.expect(TT::LParen, "'('");
+
+			let mut args = Vec::new();
+			let mut rests = Vec::new();
+			loop {
+				if self.catch_one(TT::RParen).is_some() {
+					break;
+				}
+
+				if let Some(op) = self.catch_one(TT::DotDotDot) {
+					rests.push((args.len(), op));
+				}
+				
+				let Some(name) = self.expect(TT::Identifier, "parameter name") else {
+					break;
+				};
+
+				let annotation =
+					if self.catch_one(TT::Colon).is_some() {
+						Some(self.type_expression())
+					} else {
+						None
+					};
+				
+				args.push((name, annotation));
+
+				self.catch_one(TT::Comma);
+			}
+
+			let mut variadic = false;
+			for (i, op) in rests {
+				if i + 1 == args.len() {
+					variadic = true;
+				} else {
+					self.report.error_at(op.span(), "a rest parameter must be the last parameter".to_string());
+				}
+			}
+
+			let ret =
+				if self.catch_one(TT::Colon).is_some() {
+					Some(self.type_expression())
+				} else {
+					None
+				};
+
+			let where_clause = self.catch_one(TT::Where).map(|op| self.where_clause(op));
+
+			self.expect(TT::EqualGreater, "'=>'");
+
+			let expr = self.expression();
+
+			let index = self.add(Node::Fn { name, args, variadic, ret, where_clause, expr });
+			self.widen(index, keyword)
+		} else {
+			self.jump()
+		}
+	}
+
+	fn where_clause(&mut self, op: &'a Token) -> NodeIndex {
+		let mut constraints = Vec::new();
+		while let Some(name) = self.expect(TT::Identifier, "type parameter name") {
+			self.expect(TT::Colon, "':'");
+			constraints.push((name, self.type_expression()));
+			if self.catch_one(TT::Comma).is_none() {
+				break;
+			}
+		}
+		self.add(Node::Where { op, constraints })
+	}
+
+	fn jump(&mut self) -> NodeIndex {
+		if let Some(mut op) = self.catch_one(TT::If) {
+			// an `else if` chain is parsed in a loop rather than recursively,
+			// so a long chain doesn't run out of stack. the ifs are added
+			// innermost first, which keeps the nodes in post-order
+			let mut chain = vec![];
+			let mut else_branch = loop {
+				let pattern =
+					if self.catch_one(TT::Let).is_some() {
+						let Some(pattern) = self.pattern() else {
+							return self.add(Node::Error);
+						};
+						self.expect(TT::Equal, "'='");
+						Some(pattern)
+					} else {
+						None
+					};
+
+				let condition = self.condition();
+
+				let then_branch = self.expression();
+				chain.push((op, pattern, condition, then_branch));
+
+				if self.catch_one(TT::Else).is_none() {
+					break None;
+				}
+				match self.catch_one(TT::If) {
+					Some(next) => op = next,
+					None => break Some(self.expression()),
+				}
+			};
+
+			while let Some((op, pattern, condition, then_branch)) = chain.pop() {
+				else_branch = Some(match pattern {
+					Some(pattern) => self.add(Node::IfLet { op, pattern, expr: condition, then_branch, else_branch }),
+					None => self.add(Node::If { op, condition, then_branch, else_branch }),
+				});
+			}
+			else_branch.unwrap()
+		} else if let Some(label) = self.catch_one(TT::Label) {
+			self.expect(TT::Colon, "':' after label");
+			if matches!(self.peek().kind, TT::Loop | TT::While | TT::Repeat) {
+				self.looping(Some(label))
+			} else {
+				let found = self.peek();
+				self.report.error_at(found.span(), format!("expected a loop after label, found {}", self.describe(found)));
+				self.add(Node::Error)
+			}
+		} else if matches!(self.peek().kind, TT::Loop | TT::While | TT::Repeat) {
+			self.looping(None)
+		} else if let Some(op) = self.catch_one(TT::Break) {
+			let label = self.catch_one(TT::Label);
+			// a value has to start on the same line, so `break` at the end of
+			// a line doesn't swallow the next statement. `while` always ends
+			// `repeat break while x`, and a while loop is Unit anyway
+			let next = self.peek();
+			let same_line = next.line() == label.unwrap_or(op).line();
+			let starts = next.kind == TT::Identifier || next.kind.is_literal() || EXPRESSION_STARTS.contains(&next.kind);
+			let starts = starts && next.kind != TT::While;
+			let value = (same_line && starts).then(|| self.expression());
+			self.add(Node::Break { op, label, value })
+		} else if let Some(op) = self.catch_one(TT::Continue) {
+			let label = self.catch_one(TT::Label);
+			self.add(Node::Continue { op, label })
+		} else {
+			self.binary(0)
+		}
+	}
+
+	fn looping(&mut self, label: Option<&'a Token>) -> NodeIndex {
+		if let Some(op) = self.catch_one(TT::While) {
+			let condition = self.condition();
+			let body = self.expression();
+			self.add(Node::While { op, label, condition, body })
+		} else if let Some(op) = self.catch_one(TT::Repeat) {
+			let body = self.expression();
+			self.expect(TT::While, "'while' after the body of 'repeat'");
+			let condition = self.condition();
+			self.add(Node::RepeatWhile { op, label, body, condition })
+		} else {
+			let op = self.next();
+			let body = self.expression();
+			self.add(Node::Loop { op, label, body })
+		}
+	}
+
+	fn condition(&mut self) -> NodeIndex {
+		let outer = std::mem::replace(&mut self.condition, true);
+		let index = self.binary(0);
+		self.condition = outer;
+		index
+	}
+
+	// binary operators by precedence climbing. operators binding looser
+	// than `min` are left for a caller further up
+	fn binary(&mut self, min: u8) -> NodeIndex {
+		let mut left = self.unary();
+		// the precedence of the last non-associative operator at this level
+		let mut chained = None;
+		while let Some((precedence, associativity)) = self.operators.get(self.peek().kind) {
+			if precedence < min {
+				break;
+			}
+			let op = self.next();
+			// `a < b < c` would otherwise compare a Bool against c
+			if associativity == Associativity::None && chained == Some(precedence) {
+				let comparison = matches!(
+					op.kind,
+					TT::EqualEqual | TT::BangEqual | TT::Lesser | TT::LesserEqual | TT::Greater | TT::GreaterEqual,
+				);
+				let message = if comparison {
+					"comparison operators cannot be chained, use parentheses to group them".to_string()
+				} else {
+					format!("'{}' cannot be chained, use parentheses to group it", op.get(self.src))
+				};
+				self.report.error_at(op.span(), message);
+			}
+			let right = match associativity {
+				Associativity::Right => self.binary(precedence),
+				Associativity::Left | Associativity::None => self.binary(precedence + 1),
+			};
+			left = self.add(Node::Binary { left, op, right });
+			if associativity == Associativity::None {
+				chained = Some(precedence);
+			}
+		}
+		left
+	}
+
+	fn unary(&mut self) -> NodeIndex {
+		if self.peek().kind.is_unary_op() {
+			let op = self.next();
+			let right = self.unary();
+			self.add(Node::Unary { op, right })
+		} else {
+			self.call()
+		}
+	}
+
+	fn call(&mut self) -> NodeIndex {
+		let mut expr = self.primary();
+
+		loop {
+			if let Some(open) = self.catch_one(TT::LParen) {
+				let outer = std::mem::replace(&mut self.condition, false);
+				let mut args = vec![];
+				if self.peek().kind != TT::RParen {
+					loop {
+						args.push(self.expression());
+						if self.catch_one(TT::Comma).is_none() {
+							break;
+						}
+					}
+				}
+				self.condition = outer;
+				let Some(op) = self.close(open, TT::RParen, "')'") else {
+					return self.add(Node::Error);
+				};
+				// `f(x) { ... }` passes the block as a last argument. it has to
+				// open on the line the call closes on, so a block statement on
+				// the next line stays separate
+				if !self.condition && self.peek().kind == TT::LBrace && self.peek().line() == op.line() {
+					args.push(self.trailing());
+				}
+				expr = self.add(Node::Call { op, expr, args })
+			} else if let Some(op) = self.catch_one(TT::LBracket) {
+				expr = self.index(op, expr);
+			} else if let Some(op) = self.catch_one(TT::Dot) {
+				let found = self.peek();
+				if !matches!(found.kind, TT::Identifier | TT::Integer) {
+					self.report.error_at(found.span(), format!("expected a field name after '.', found {}", self.describe(found)));
+					return self.add(Node::Error);
+				}
+				let name = self.next();
+				expr = self.add(Node::Field { expr, op, name });
+			} else if let Some(op) = self.catch_one(TT::Question) {
+				expr = self.add(Node::Try { expr, op })
+			} else {
+				break;
+			}
+		}
+
+		expr
+	}
+
+	// a trailing block after a call, either a plain block or a function
+	// written `{ a, b => ... }`
+	fn trailing(&mut self) -> NodeIndex {
+		let open = self.next();
+
+		let mut n = 0;
+		while self.peek_nth(n).is_some_and(|v| v.kind == TT::Identifier) {
+			n += 1;
+			if self.peek_nth(n).is_none_or(|v| v.kind != TT::Comma) {
+				break;
+			}
+			n += 1;
+		}
+		let function = n > 0 && self.peek_nth(n).is_some_and(|v| v.kind == TT::EqualGreater);
+		if !function {
+			let index = self.block(Some(open));
+			return self.widen(index, open);
+		}
+
+		let mut args = vec![];
+		while let Some(name) = self.catch_one(TT::Identifier) {
+			args.push((name, None));
+			self.catch_one(TT::Comma);
+		}
+		self.expect(TT::EqualGreater, "'=>'");
+		let expr = self.block(Some(open));
+		let index = self.add(Node::Fn { name: None, args, variadic: false, ret: None, where_clause: None, expr });
+		self.widen(index, open)
+	}
+
+	// parses the rest of `expr[i]` or a slice like `expr[a..b]`, after the `[`
+	fn index(&mut self, op: &'a Token, expr: NodeIndex) -> NodeIndex {
+		let start =
+			if self.peek().kind == TT::DotDot {
+				None
+			} else {
+				Some(self.expression())
+			};
+
+		let node = match (self.catch_one(TT::DotDot), start) {
+			(Some(dots), start) => {
+				let end =
+					if self.peek().kind == TT::RBracket {
+						None
+					} else {
+						Some(self.expression())
+					};
+				let range = self.add(Node::Range { start, op: dots, end });
+				Node::Slice { op, expr, range }
+			}
+			(None, Some(index)) => Node::Index { op, expr, index },
+			(None, None) => unreachable!("a missing start is only skipped for '..'"),
+		};
+
+		if self.expect(TT::RBracket, "']'").is_none() {
+			return self.add(Node::Error);
+		}
+		self.add(node)
+	}
+
+	fn primary(&mut self) -> NodeIndex {
+		// brackets close off a condition, so trailing blocks work inside them
+		let outer = self.condition;
+		if matches!(self.peek().kind, TT::LParen | TT::LBracket | TT::LBrace) {
+			self.condition = false;
+		}
+		let index = self.operand();
+		self.condition = outer;
+		index
+	}
+
+	fn operand(&mut self) -> NodeIndex {
+		let kind = self.peek().kind;
+
+		match kind {
+			TT::Identifier => {
+				let name = self.next();
+				self.add(Node::Identifier { name })
+			}
+
+			TT::True => {
+				self.next();
+				self.add(Node::Bool { value: true })
+			}
+			TT::False => {
+				self.next();
+				self.add(Node::Bool { value: false })
+			}
+			TT::Nil => {
+				self.next();
+				self.add(Node::Nil)
+			}
+
+			TT::Integer => {
+				let value = self.next();
+				self.add(Node::Integer { value })
+			}
+			TT::Float => {
+				let value = self.next();
+				self.add(Node::Float { value })
+			}
+
+			TT::LParen => {
+				let open = self.next();
+				let expr = self.expression();
+				if self.catch_one(TT::Comma).is_some() {
+					let index = self.tuple(open, expr);
+					return self.widen(index, open);
+				}
+				let ty =
+					if self.catch_one(TT::Colon).is_some() {
+						Some(self.type_expression())
+					} else {
+						None
+					};
+				let index = if self.close(open, TT::RParen, "')'").is_none() {
+					self.add(Node::Error)
+				} else if let Some(ty) = ty {
+					self.add(Node::Ascribe { expr, ty })
+				} else {
+					self.add(Node::Group { expr })
+				};
+				self.widen(index, open)
+			}
+			TT::LBracket => {
+				let open = self.next();
+				let mut items = vec![];
+				while self.catch_one(TT::RBracket).is_none() {
+					items.push(self.expression());
+					if self.catch_one(TT::Comma).is_none() {
+						if self.close(open, TT::RBracket, "',' or ']'").is_none() {
+							return self.add(Node::Error);
+						}
+						break;
+					}
+				}
+				let index = self.add(Node::Array { items });
+				self.widen(index, open)
+			}
+			TT::LBrace => {
+				let open = self.next();
+				// `{ name = ...` is a record only once a ',' follows its first
+				// field, like a tuple of one element. otherwise it's a block
+				// starting with an assignment, as in `while c { x = 1 }`
+				let record = self.peek_nth(0).is_some_and(|v| v.kind == TT::Identifier)
+					&& self.peek_nth(1).is_some_and(|v| v.kind == TT::Equal);
+				let index = if record {
+					let checkpoint = self.checkpoint();
+					match self.record(open) {
+						Some(index) => index,
+						None => {
+							self.rollback(checkpoint);
+							self.block(Some(open))
+						}
+					}
+				} else {
+					self.block(Some(open))
+				};
+				self.widen(index, open)
+			}
+
+			// reserved for later, so they can't be used as names in the meantime
+			TT::For | TT::Struct | TT::Module => {
+				let token = self.next();
+				self.report.error_at(token.span(), format!("'{}' is not supported yet", token.get(self.src)));
+				self.add(Node::Error)
+			}
+
+			_ => {
+				// recover by skipping the token, which is a no-op at the end of
+				// input
+				let token = self.next();
+				let starts = EXPRESSION_STARTS.iter()
+					.filter_map(|v| v.symbol())
+					.map(|v| format!("'{}'", v))
+					.collect::<Vec<_>>();
+				self.report.error_at(token.span(), format!(
+					"expected expression, found {}. an expression starts with an identifier, a literal, or one of {}",
+					self.describe(token), starts.join(", "),
+				));
+				self.add(Node::Error)
+			}
+		}
+	}
+
+	// parses the rest of a tuple after its first element and comma, so
+	// `(a,)` is a tuple of one element while `(a)` stays a group
+	fn tuple(&mut self, open: &Token, first: NodeIndex) -> NodeIndex {
+		let mut items = vec![first];
+		while self.catch_one(TT::RParen).is_none() {
+			items.push(self.expression());
+			if self.catch_one(TT::Comma).is_none() {
+				if self.close(open, TT::RParen, "',' or ')'").is_none() {
+					return self.add(Node::Error);
+				}
+				break;
+			}
+		}
+		self.add(Node::Tuple { items })
+	}
+
+	// parses a record after its '{', or gives `None` if its first field
+	// isn't followed by a ',', since then it's a block instead
+	fn record(&mut self, open: &Token) -> Option<NodeIndex> {
+		let mut fields = Vec::new();
+		while self.catch_one(TT::RBrace).is_none() {
+			let Some(name) = self.expect(TT::Identifier, "field name") else {
+				return Some(self.add(Node::Error));
+			};
+			self.expect(TT::Equal, "'='");
+			let value = self.expression();
+			fields.push((name, value));
+
+			if self.catch_one(TT::Comma).is_none() {
+				if fields.len() == 1 {
+					return None;
+				}
+				if self.close(open, TT::RBrace, "',' or '}'").is_none() {
+					return Some(self.add(Node::Error));
+				}
+				break;
+			}
+		}
+		Some(self.add(Node::Record { fields }))
+	}
+
+	fn type_expression(&mut self) -> NodeIndex {
+		let mut ty = self.type_primary();
+		while let Some(op) = self.catch_one(TT::Question) {
+			ty = self.add(Node::Optional { ty, op });
+		}
+		ty
+	}
+
+	fn type_primary(&mut self) -> NodeIndex {
+		let kind = self.peek().kind;
+
+		match kind {
+			TT::Identifier => {
+				let name = self.next();
+				self.add(Node::Identifier { name })
+			}
+			TT::LBracket => {
+				let open = self.next();
+				let ty = self.type_expression();
+				self.expect(TT::RBracket, "']'");
+				let index = self.add(Node::ArrayType { ty });
+				self.widen(index, open)
+			}
+			_ => {
+				let token = self.next();
+				self.report.error_at(token.span(), format!("expected type, found {}", self.describe(token)));
+				self.add(Node::Error)
+			}
+		}
+	}
+
+}
+
+pub fn parse<'a>(src: &'a str, tokens: &'a TokenStream<'a>) -> Result<Ast<'a>, Report> {
+	Parser::new(src, tokens).build()
+}
+
+/// parses with different binary operator precedences than the default
+pub fn parse_with<'a>(src: &'a str, tokens: &'a TokenStream<'a>, operators: &OperatorTable) -> Result<Ast<'a>, Report> {
+	let mut parser = Parser::new(src, tokens);
+	parser.operators = operators.clone();
+	parser.build()
+}
+
+/// an [`Ast`] that owns its source and tokens, so it can be stored or returned
+/// without borrowing from the caller.
+///
+/// nodes refer to tokens by reference, which can't point into the same
+/// struct. so the tree is parsed once and kept with each token swapped for
+/// its index in the stream, and [`OwnedAst::borrow`] swaps them back
+#[derive(Debug)]
+pub struct OwnedAst {
+	tokens: TokenStream<'static>,
+	// every token in `nodes` is `PLACEHOLDER`, and the real ones are in
+	// `indices`, in the order `Node::map_tokens` visits them
+	nodes: Vec<Node<'static>>,
+	indices: Vec<u32>,
+	spans: Vec<Span>,
+	root: NodeIndex,
+}
+
+static PLACEHOLDER: Token = Token::new(TT::Eof, (0, 0), (0, 0));
+
+impl OwnedAst {
+	pub fn new(src: String) -> Result<Self, Report> {
+		Self::with_options(src, TokenizeOptions::default(), &OperatorTable::default())
+	}
+
+	pub fn with_options(src: String, options: TokenizeOptions, operators: &OperatorTable) -> Result<Self, Report> {
+		let tokens = tokenize_with(&src, options)?.tokens;
+		let tokens = TokenStream::new(src, tokens);
+		let ast = parse_with(&tokens.src, &tokens, operators)?;
+
+		// tokens are stored in source order and no two start at the same
+		// offset, so a token's index is found by where it starts
+		let mut indices = Vec::new();
+		let nodes = ast.nodes.iter()
+			.map(|node| node.map_tokens(&mut |token| {
+				let index = tokens.tokens.binary_search_by_key(&token.span().start, |v| v.span().start)
+					.ok()
+					.filter(|&index| std::ptr::eq(&tokens.tokens[index], token))
+					.expect("node holds a token from outside the stream");
+				indices.push(index as u32);
+				&PLACEHOLDER
+			}))
+			.collect();
+		Ok(Self { nodes, indices, spans: ast.spans, root: ast.root, tokens })
+	}
+
+	pub fn src(&self) -> &str {
+		&self.tokens.src
+	}
+
+	pub fn tokens(&self) -> &TokenStream<'static> {
+		&self.tokens
+	}
+
+	/// the tree, pointing into this [`OwnedAst`]'s tokens. this doesn't parse
+	/// again, but every call clones all the nodes and spans, so keep the
+	/// result around rather than calling it per lookup
+	pub fn borrow(&self) -> Ast<'_> {
+		let mut indices = self.indices.iter();
+		let nodes = self.nodes.iter()
+			.map(|node| node.map_tokens(&mut |_| &self.tokens.tokens[*indices.next().unwrap() as usize]))
+			.collect();
+		Ast {
+			tokens: &self.tokens,
+			nodes,
+			spans: self.spans.clone(),
+			root: self.root.clone(),
+		}
+	}
+}
+
+/// renames every identifier spelled `from`, including the names introduced
+/// by `let` and function parameters. `to_token` must be a token from the same
+/// source as `ast`, since token text is read through its span.
+///
+/// this matches by name only, so shadowed bindings are renamed as well.
+pub fn rename_identifier<'a>(ast: &mut Ast<'a>, from: &str, to_token: &'a Token) {
+	let src = &*ast.tokens.src;
+	for node in ast.nodes.iter_mut() {
+		match node {
+			Node::Identifier { name } if name.get(src) == from => {
+				*name = to_token;
+			}
+			Node::Let { pattern, .. } => {
+				for name in pattern.names_mut() {
+					if name.get(src) == from {
+						*name = to_token;
+					}
+				}
+			}
+			Node::Fn { name, args, .. } => {
+				for name in name.iter_mut().chain(args.iter_mut().map(|(name, _)| name)) {
+					if name.get(src) == from {
+						*name = to_token;
+					}
+				}
+			}
+			_ => (),
+		}
+	}
+}
+
+/// the `///` comments right above each declaration, keyed by the declaration
+/// node, with the `///` and one space after it stripped from every line.
+/// `tokens` has to be tokenized with `keep_comments`, otherwise there's
+/// nothing to find. other comments are ignored, and a blank line or a plain
+/// comment between the doc comment and the declaration detaches it
+pub fn doc_comments(ast: &Ast, tokens: &TokenStream) -> HashMap<NodeIndex, String> {
+	let mut docs = HashMap::new();
+	for node in &ast.nodes {
+		let Node::Block { expr } = node else { continue };
+		for index in expr {
+			let declaration = match ast.get(index) {
+				Node::Fn { name, .. } => name.is_some(),
+				Node::Let { .. } | Node::Const { .. } | Node::TypeAlias { .. } | Node::Enum { .. }
+				| Node::Export { .. } | Node::Attribute { .. } => true,
+				_ => false,
+			};
+			if !declaration {
+				continue;
+			}
+
+			let start = ast.span(index).start;
+			let Some(first) = tokens.iter().position(|v| v.span().start == start) else { continue };
+			let mut line = tokens.get(first).line();
+			let mut lines = vec![];
+			for token in tokens.tokens[..first].iter().rev() {
+				let text = tokens.str_from(token);
+				let doc = token.kind == TT::LineComment && text.starts_with("///") && !text.starts_with("////");
+				if !doc || token.line() + 1 != line {
+					break;
+				}
+				let text = &text[3..];
+				lines.push(text.strip_prefix(' ').unwrap_or(text).trim_end());
+				line = token.line();
+			}
+			if !lines.is_empty() {
+				lines.reverse();
+				docs.insert(index.clone(), lines.join("\n"));
+			}
+		}
+	}
+	docs
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::{parse::*, token::{tokenize, tokenize_with, TokenizeOptions}};
+
+	#[test]
+	fn binary() {
+		let src = "1 + 1";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		println!("{:#?}", ast);
+	}
+
+	#[test]
+	fn call() {
+		let src = "let f = fn (a) => a + a";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		println!("{:#?}", ast);
+	}
+
+	#[test]
+	fn owned() {
+		let owned = {
+			let src = String::from("let x = 1 + 2");
+			OwnedAst::new(src.clone()).unwrap()
+		};
+
+		let ast = owned.borrow();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::Let { pattern: Pattern::Identifier { name }, expr, .. } = ast.get(&expr[0]) else { panic!() };
+		assert_eq!(name.get(owned.src()), "x");
+		let children = ast.children(expr);
+		assert_eq!(children.len(), 2);
+		// borrowing again gives the same tree
+		assert_eq!(owned.borrow().dump(), ast.dump());
+
+		// every token comes back in its place
+		let src = "export let f = fn (a: Int, ...rest): Int => { if a < 1 0 else f(a - 1)? };
+			'outer: while !x { loop { break 'outer } }; if let (b, c) = x[1..] { c = -b[..2].d }";
+		let tokens = tokenize(src).unwrap();
+		let owned = OwnedAst::new(String::from(src)).unwrap();
+		assert_eq!(owned.borrow().dump(), parse(src, &tokens).unwrap().dump());
+
+		assert!(OwnedAst::new(String::from("let = 1")).is_err());
+
+		let mut operators = OperatorTable::default();
+		operators.set(TT::Minus, 2, Associativity::Right);
+		let owned = OwnedAst::with_options(String::from("1 - 2 - 3"), TokenizeOptions::default(), &operators).unwrap();
+		assert_eq!(owned.borrow().dump(), "(binary - (int 1) (binary - (int 2) (int 3)))");
+
+		let options = TokenizeOptions { max_identifier_len: Some(3), ..Default::default() };
+		assert!(OwnedAst::with_options(String::from("let long = 1"), options, &OperatorTable::default()).is_err());
+	}
+
+	#[test]
+	fn kind() {
+		let src = "let f = fn (a) => -a; f(1 + 2)";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		assert_eq!(
+			ast.nodes.iter().map(|v| v.kind()).collect::<Vec<_>>(),
+			vec![
+				NodeKind::Identifier, NodeKind::Unary, NodeKind::Fn, NodeKind::Let,
+				NodeKind::Identifier, NodeKind::Integer, NodeKind::Integer, NodeKind::Binary, NodeKind::Call,
+				NodeKind::Block, NodeKind::Module,
+			],
+		);
+	}
+
+	#[test]
+	fn children() {
+		let src = "1 + 2";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let children = ast.children(&expr[0]);
+		assert_eq!(children.len(), 2);
+		assert!(children.iter().all(|v| matches!(ast.get(v), Node::Integer { .. })));
+		assert!(ast.children(&children[0]).is_empty());
+
+		let src = "f(1, x)";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let children = ast.children(&expr[0]);
+		assert_eq!(children.len(), 3);
+		assert!(matches!(ast.get(&children[0]), Node::Identifier { .. }));
+		assert!(matches!(ast.get(&children[1]), Node::Integer { .. }));
+		assert!(matches!(ast.get(&children[2]), Node::Identifier { .. }));
+	}
+
+	#[test]
+	fn ascribe() {
+		let src = "(0: Float)";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::Ascribe { expr, ty } = ast.get(&expr[0]) else { panic!() };
+		assert!(matches!(ast.get(expr), Node::Integer { .. }));
+		assert!(matches!(ast.get(ty), Node::Identifier { .. }));
+
+		let src = "(0)";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		assert!(ast.nodes.iter().any(|v| matches!(v, Node::Group { .. })));
+	}
+
+	#[test]
+	fn try_operator() {
+		let src = "foo()?";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::Try { expr, .. } = ast.get(&expr[0]) else { panic!() };
+		assert!(matches!(ast.get(expr), Node::Call { .. }));
+
+		let src = "a?(1)?";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::Try { expr, .. } = ast.get(&expr[0]) else { panic!() };
+		let Node::Call { expr, .. } = ast.get(expr) else { panic!() };
+		assert!(matches!(ast.get(expr), Node::Try { .. }));
+	}
+
+	#[test]
+	fn record() {
+		let src = "{ x = 1, y = 2, }";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::Record { fields } = ast.get(&expr[0]) else { panic!() };
+		assert_eq!(fields.iter().map(|(v, _)| v.get(src)).collect::<Vec<_>>(), vec!["x", "y"]);
+
+		for src in ["{ x }", "{ x == 1 }", "{ let x = 1; x }", "{ }", "{ x = 1; x }", "{ x = 1 }"] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+			let Node::Block { expr } = ast.get(root) else { panic!() };
+			assert!(matches!(ast.get(&expr[0]), Node::Block { .. }), "{}", src);
+		}
+
+		// one field needs a trailing ',' to be a record
+		let src = "{ x = 1, }";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		assert!(matches!(ast.get(&expr[0]), Node::Record { .. }));
+
+		// bodies that assign stay blocks
+		for src in ["mut i = 0; while i < 3 { i = i + 1 }; i", "mut x = 0; if true { x = 1 }"] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			assert!(!ast.dump().contains("(record"), "{}", src);
+		}
+
+		let src = "{ x = 1, y = 2 z = 3 }";
+		let tokens = tokenize(src).unwrap();
+		let report = parse(src, &tokens).unwrap_err();
+		assert_eq!(report.errors()[0].message, "expected ',' or '}', found 'z'");
+	}
+
+	#[test]
+	fn loops() {
+		let src = "'outer: loop { while true { break 'outer; continue } }";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::Loop { label: Some(label), body, .. } = ast.get(&expr[0]) else { panic!() };
+		assert_eq!(label.get(src), "'outer");
+		let Node::Block { expr } = ast.get(body) else { panic!() };
+		let Node::While { label: None, body, .. } = ast.get(&expr[0]) else { panic!() };
+		let Node::Block { expr } = ast.get(body) else { panic!() };
+		assert!(matches!(ast.get(&expr[0]), Node::Break { label: Some(_), value: None, .. }));
+		assert!(matches!(ast.get(&expr[1]), Node::Continue { label: None, .. }));
+
+		let src = "'outer: 1";
+		let tokens = tokenize(src).unwrap();
+		let report = parse(src, &tokens).unwrap_err();
+		assert_eq!(report.errors()[0].message, "expected a loop after label, found '1'");
+	}
+
+	#[test]
+	fn rename() {
+		let src = "let x = 1; x + x; y";
+		let tokens = tokenize(src).unwrap();
+		let mut ast = parse(src, &tokens).unwrap();
+
+		let to_token = tokens.iter().rfind(|v| v.kind == TT::Identifier).unwrap();
+		rename_identifier(&mut ast, "x", to_token);
+
+		let names = ast.nodes.iter()
+			.flat_map(|v| match v {
+				Node::Identifier { name } => vec![name.get(src)],
+				Node::Let { pattern, .. } => pattern.names().iter().map(|v| v.get(src)).collect(),
+				_ => vec![],
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(names, vec!["y", "y", "y", "y"]);
+	}
+
+	#[test]
+	fn chained_comparison() {
+		let src = "1 < 2 < 3";
+		let tokens = tokenize(src).unwrap();
+		let report = parse(src, &tokens).unwrap_err();
+		assert_eq!(report.errors()[0].message, "comparison operators cannot be chained, use parentheses to group them");
+		assert_eq!(report.errors()[0].span, Some(Span::new(6, 7)));
+
+		let src = "(1 < 2) == true";
+		let tokens = tokenize(src).unwrap();
+		assert!(parse(src, &tokens).is_ok());
+	}
+
+	#[test]
+	fn expect() {
+		for (src, message) in [
+			("let = 1", "expected identifier, found '='"),
+			("let x 1", "expected '=', found '1'"),
+			("fn a) => a", "expected '(', found ')'"),
+			("fn (1) => 1", "expected parameter name, found '1'"),
+			("fn (a) a", "expected '=>', found 'a'"),
+			("f(1 2)", "expected ')', found '2'"),
+			("(1 2)", "expected ')', found '2'"),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let report = parse(src, &tokens).unwrap_err();
+			assert_eq!(report.errors()[0].message, message, "{}", src);
+		}
+
+		let src = "let = 1";
+		let tokens = tokenize(src).unwrap();
+		let report = parse(src, &tokens).unwrap_err();
+		assert_eq!(report.errors()[0].span, Some(Span::new(4, 5)));
+
+		// the end of input is at the end of the source
+		for (src, span) in [("let x =", Span::new(7, 7)), ("1 +", Span::new(3, 3))] {
+			let tokens = tokenize(src).unwrap();
+			let report = parse(src, &tokens).unwrap_err();
+			assert_eq!(report.errors()[0].span, Some(span), "{}", src);
+		}
+	}
+
+	#[test]
+	fn unclosed() {
+		for (src, message, span) in [
+			("let x = (1", "unclosed '(' started here", Span::new(8, 9)),
+			("f(1", "unclosed '(' started here", Span::new(1, 2)),
+			("(1, 2", "unclosed '(' started here", Span::new(0, 1)),
+			("x = [1, 2", "unclosed '[' started here", Span::new(4, 5)),
+			("if x {\n\t1", "unclosed '{' started here", Span::new(5, 6)),
+			("{ a = 1, b = 2", "unclosed '{' started here", Span::new(0, 1)),
+			("f(x) { y", "unclosed '{' started here", Span::new(5, 6)),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let report = parse(src, &tokens).unwrap_err();
+			assert_eq!(report.errors()[0].message, message, "{}", src);
+			assert_eq!(report.errors()[0].span, Some(span), "{}", src);
+		}
+	}
+
+	#[test]
+	fn tuple() {
+		let src = "let (a, (b, c)) = (1, (2, 3),)";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::Let { pattern, expr, .. } = ast.get(&expr[0]) else { panic!() };
+		let names = pattern.names().iter().map(|v| v.get(src)).collect::<Vec<_>>();
+		assert_eq!(names, vec!["a", "b", "c"]);
+		let Node::Tuple { items } = ast.get(expr) else { panic!() };
+		assert_eq!(items.len(), 2);
+		assert_eq!(ast.get(&items[1]).kind(), NodeKind::Tuple);
+	}
+
+	#[test]
+	fn comments() {
+		let src = "let x = /* one */ 1 // two";
+		let options = TokenizeOptions { keep_comments: true, ..Default::default() };
+		let tokens = tokenize_with(src, options).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		assert_eq!(expr.len(), 1);
+	}
+
+	#[test]
+	fn trivia_tree() {
+		let shape = |src: &str| {
+			let options = TokenizeOptions { keep_comments: true, ..Default::default() };
+			let tokens = tokenize_with(src, options).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			(0..ast.nodes.len())
+				.map(|i| {
+					let index = NodeIndex(i as u32);
+					(ast.get(&index).kind(), ast.children(&index))
+				})
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(shape("1 /* c */ + 1"), shape("1 + 1"));
+
+		// spans still point at the real token after skipped trivia
+		let src = "let /* c */ = 1";
+		let options = TokenizeOptions { keep_comments: true, ..Default::default() };
+		let tokens = tokenize_with(src, options).unwrap();
+		let report = parse(src, &tokens).unwrap_err();
+		assert_eq!(report.errors()[0].span, Some(Span::new(12, 13)));
+	}
+
+	#[test]
+	fn expected_expression() {
+		let src = ") + 1";
+		let tokens = tokenize(src).unwrap();
+		let report = parse(src, &tokens).unwrap_err();
+		assert_eq!(
+			report.errors()[0].message,
+			"expected expression, found ')'. an expression starts with an identifier, a literal, \
+			or one of '(', '[', '{', '-', '!', 'fn', 'if', 'loop', 'while', 'repeat', 'break', 'continue'",
+		);
+		assert_eq!(report.errors()[0].span, Some(Span::new(0, 1)));
+	}
+
+	#[test]
+	fn malformed() {
+		let corpus = [
+			"fn", "let", "mut", "(((", ")))", "=>", "{", "}", "{ x = ", "{ x = 1,",
+			"fn (", "fn (a", "fn (a) =>", "fn (a: ) => a", "let x", "let x =", "let (a, ",
+			"if", "if true", "if true 1 else", "loop", "'a:", "'a: 1", "while", "while true",
+			"f(", "f(1,", "(1,", "(1: ", "1 +", "- -", "!", "1 == 2 == 3", "?", "* / %",
+			"+ - * / % = == != < > <= >= ! ? : ; , .", "let = = =", "{ { { }",
+			"#", "\"abc", "r#\"", "'", "/* ", "@ fn",
+		];
+		for src in corpus {
+			// either stage may reject the input, as long as neither panics
+			if let Ok(tokens) = tokenize(src) {
+				assert!(parse(src, &tokens).is_err(), "{}", src);
+			}
+		}
+	}
+
+	#[test]
+	fn assign() {
+		let src = "a = b = 1";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::Assign { target, value, .. } = ast.get(&expr[0]) else { panic!() };
+		assert!(matches!(ast.get(target), Node::Identifier { name } if name.get(src) == "a"));
+		let Node::Assign { target, value, .. } = ast.get(value) else { panic!() };
+		assert!(matches!(ast.get(target), Node::Identifier { name } if name.get(src) == "b"));
+		assert!(matches!(ast.get(value), Node::Integer { .. }));
+
+		let src = "1 = 2";
+		let tokens = tokenize(src).unwrap();
+		let report = parse(src, &tokens).unwrap_err();
+		assert_eq!(report.errors()[0].message, "invalid assignment target");
+	}
+
+	#[test]
+	fn node_map() {
+		let mut map = NodeMap::new();
+		assert_eq!(map.insert(&NodeIndex(3), "three"), None);
+		assert_eq!(map.insert(&NodeIndex(1), "one"), None);
+		assert_eq!(map.insert(&NodeIndex(3), "tres"), Some("three"));
+
+		assert_eq!(map.get(&NodeIndex(1)), Some(&"one"));
+		assert_eq!(map.get(&NodeIndex(2)), None);
+		assert_eq!(map.get(&NodeIndex(10)), None);
+
+		let items = map.iter().collect::<Vec<_>>();
+		assert_eq!(items, vec![(NodeIndex(1), &"one"), (NodeIndex(3), &"tres")]);
+	}
+
+	#[test]
+	fn export() {
+		let src = "export let x = 1; export fn (a) => a; export mut y = 2";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::Export { item, .. } = ast.get(&expr[0]) else { panic!() };
+		assert_eq!(ast.get(item).kind(), NodeKind::Let);
+		let Node::Export { item, .. } = ast.get(&expr[1]) else { panic!() };
+		assert_eq!(ast.get(item).kind(), NodeKind::Fn);
+		let Node::Export { item, .. } = ast.get(&expr[2]) else { panic!() };
+		assert!(matches!(ast.get(item), Node::Let { mutable: true, .. }));
+
+		let src = "export 1 + 1";
+		let tokens = tokenize(src).unwrap();
+		let report = parse(src, &tokens).unwrap_err();
+		assert_eq!(report.errors()[0].message, "expected 'let', 'mut', or 'fn' after 'export', found '1'");
+		assert_eq!(report.errors()[0].span, Some(Span::new(7, 8)));
+	}
+
+	#[test]
+	fn if_let() {
+		let src = "if let x = foo { x } else { 0 }";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::IfLet { pattern, expr, then_branch, else_branch, .. } = ast.get(&expr[0]) else { panic!() };
+		assert!(matches!(pattern, Pattern::Identifier { name } if name.get(src) == "x"));
+		assert!(matches!(ast.get(expr), Node::Identifier { name } if name.get(src) == "foo"));
+		assert_eq!(ast.get(then_branch).kind(), NodeKind::Block);
+		assert!(else_branch.is_some());
+	}
+
+	#[test]
+	fn slice() {
+		for (src, start, end) in [("a[1..3]", true, true), ("a[..2]", false, true), ("a[1..]", true, false)] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+			let Node::Block { expr } = ast.get(root) else { panic!() };
+			let Node::Slice { range, .. } = ast.get(&expr[0]) else { panic!("{}", src) };
+			let Node::Range { start: s, end: e, .. } = ast.get(range) else { panic!("{}", src) };
+			assert_eq!((s.is_some(), e.is_some()), (start, end), "{}", src);
+		}
+
+		let src = "a[0]";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::Index { index, .. } = ast.get(&expr[0]) else { panic!() };
+		assert_eq!(ast.get(index).kind(), NodeKind::Integer);
+	}
+
+	#[test]
+	fn rest_parameter() {
+		let src = "fn (a, ...rest) => a";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::Fn { args, variadic, .. } = ast.get(&expr[0]) else { panic!() };
+		assert_eq!(args.len(), 2);
+		assert!(variadic);
+
+		let src = "fn (...rest, a) => a";
+		let tokens = tokenize(src).unwrap();
+		let report = parse(src, &tokens).unwrap_err();
+		assert_eq!(report.errors()[0].message, "a rest parameter must be the last parameter");
+		assert_eq!(report.errors()[0].span, Some(Span::new(4, 7)));
+	}
+
+	#[test]
+	fn post_order() {
+		let src = "
+			export let f = fn (a: Int, ...rest): Int => { if a < 1 0 else f(a - 1)? };
+			let (x, y) = ((1: Int), { z = 2, });
+			'outer: while !x { loop { break 'outer } };
+			if let b = x[1..] { c = -b[..2] }
+		";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		assert_eq!(ast.root.0 as usize, ast.nodes.len() - 1);
+		for i in 0..ast.nodes.len() {
+			let index = NodeIndex(i as u32);
+			for child in ast.children(&index) {
+				assert!(child.0 < index.0, "{:?} comes before its child {:?}", ast.get(&index), ast.get(&child));
+			}
+		}
+	}
+
+	#[test]
+	fn nil() {
+		let src = "let x: Int? = nil";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::Let { expr, annotation: Some(annotation), .. } = ast.get(&expr[0]) else { panic!() };
+		assert_eq!(ast.get(expr).kind(), NodeKind::Nil);
+		let Node::Optional { ty, .. } = ast.get(annotation) else { panic!() };
+		assert_eq!(ast.get(ty).kind(), NodeKind::Identifier);
+	}
+
+	#[test]
+	fn repeat_while() {
+		let src = "repeat { x } while x < 10";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		assert_eq!(expr.len(), 1);
+		let Node::RepeatWhile { body, condition, .. } = ast.get(&expr[0]) else { panic!() };
+		assert_eq!(ast.get(body).kind(), NodeKind::Block);
+		assert_eq!(ast.get(condition).kind(), NodeKind::Binary);
+
+		let src = "repeat { x } x";
+		let tokens = tokenize(src).unwrap();
+		let report = parse(src, &tokens).unwrap_err();
+		assert_eq!(report.errors()[0].message, "expected 'while' after the body of 'repeat', found 'x'");
+	}
+
+	#[test]
+	fn spans() {
+		let src = "let x = 1 + 2 * y; f(true)";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::Let { expr: value, .. } = ast.get(&expr[0]) else { panic!() };
+		assert_eq!(ast.span(value), Span::new(8, 17));
+		assert_eq!(ast.span(&expr[0]), Span::new(0, 17));
+
+		let Node::Call { args, .. } = ast.get(&expr[1]) else { panic!() };
+		assert_eq!(ast.span(&args[0]), Span::new(21, 25));
+		assert_eq!(ast.span(&expr[1]), Span::new(19, 26));
+		assert_eq!(ast.span(&ast.root), Span::new(0, 26));
+
+		let src = "a + b * c";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::Binary { right, .. } = ast.get(&expr[0]) else { panic!() };
+		assert_eq!(ast.span(&expr[0]), Span::new(0, 9));
+		assert_eq!(ast.span(right), Span::new(4, 9));
+
+		for (src, span) in [
+			("f(a)[i]", Span::new(0, 7)),
+			("(a)", Span::new(0, 3)),
+			("(a, b)", Span::new(0, 6)),
+			("{ a }", Span::new(0, 5)),
+			("{ a = 1, }", Span::new(0, 10)),
+			("{}", Span::new(0, 2)),
+			("fn () => x", Span::new(0, 10)),
+			("mut x = (1)", Span::new(0, 11)),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+			let Node::Block { expr } = ast.get(root) else { panic!() };
+			assert_eq!(ast.span(&expr[0]), span, "{}", src);
+		}
+	}
+
+	#[test]
+	fn peek_nth() {
+		let src = "a /* b */ + c";
+		let tokens = tokenize_with(src, TokenizeOptions { keep_comments: true, ..Default::default() }).unwrap();
+		let mut parser = Parser::new(src, &tokens);
+		assert_eq!(parser.peek_nth(0).unwrap().kind, TT::Identifier);
+		assert_eq!(parser.peek_nth(1).unwrap().kind, TT::Plus);
+		parser.next();
+		assert_eq!(parser.peek().kind, TT::Plus);
+		assert_eq!(parser.peek_nth(1).unwrap().get(src), "c");
+		assert_eq!(parser.peek_nth(2).unwrap().kind, TT::Eof);
+		assert!(parser.peek_nth(3).is_none());
+	}
+
+	#[test]
+	fn collect_identifiers() {
+		let src = "let x = 1; x + y";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let found = ast.collect_identifiers();
+		assert_eq!(
+			found.iter().map(|(_, name, span)| (*name, *span)).collect::<Vec<_>>(),
+			vec![("x", Span::new(11, 12)), ("y", Span::new(15, 16))],
+		);
+		for (index, name, _) in found {
+			assert!(matches!(ast.get(&index), Node::Identifier { name: v } if v.get(src) == name));
+		}
+	}
+
+	#[test]
+	fn type_alias() {
+		let src = "type Id = Int?; let x: Id = nil";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::TypeAlias { name, ty, .. } = ast.get(&expr[0]) else { panic!() };
+		assert_eq!(name.get(src), "Id");
+		assert!(matches!(ast.get(ty), Node::Optional { .. }));
+		assert_eq!(ast.span(&expr[0]), Span::new(0, 14));
+
+		let src = "type = Int";
+		let tokens = tokenize(src).unwrap();
+		let report = parse(src, &tokens).unwrap_err();
+		assert_eq!(report.errors()[0].message, "expected type name, found '='");
+	}
+
+	#[test]
+	fn catch_one() {
+		let src = "let x";
+		let tokens = tokenize(src).unwrap();
+		let mut one = Parser::new(src, &tokens);
+		let mut many = Parser::new(src, &tokens);
+		for kind in [TT::Identifier, TT::Let, TT::Let, TT::Identifier, TT::Eof, TT::Eof] {
+			assert_eq!(one.catch_one(kind), many.catch(&[kind]));
+		}
+	}
+
+	#[test]
+	fn too_many_nodes() {
+		let src = "1 + 2 + 3";
+		let tokens = tokenize(src).unwrap();
+		let mut parser = Parser::new(src, &tokens);
+		parser.max_nodes = 3;
+		let report = parser.build().unwrap_err();
+		assert_eq!(report.errors().len(), 1);
+		assert_eq!(report.errors()[0].message, "too many nodes: the maximum is 3");
+	}
+
+	#[test]
+	fn where_clause() {
+		let src = "fn (x): Int where T: Display => x";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::Fn { where_clause: Some(where_clause), .. } = ast.get(&expr[0]) else { panic!() };
+		let Node::Where { constraints, .. } = ast.get(where_clause) else { panic!() };
+		assert_eq!(constraints.len(), 1);
+		assert_eq!(constraints[0].0.get(src), "T");
+		let Node::Identifier { name } = ast.get(&constraints[0].1) else { panic!() };
+		assert_eq!(name.get(src), "Display");
+		assert_eq!(ast.span(where_clause), Span::new(12, 28));
+
+		let src = "fn (x) where T => x";
+		let tokens = tokenize(src).unwrap();
+		let report = parse(src, &tokens).unwrap_err();
+		assert_eq!(report.errors()[0].message, "expected ':', found '=>'");
+	}
+
+	#[test]
+	fn dump() {
+		for (src, dump) in [
+			("1 + 1", "(binary + (int 1) (int 1))"),
+			("let x = fn (a) => a", "(let x (fn (a) _ (ident a)))"),
+			("mut (a, b) = (1, -2.5)", "(let mut (a b) (tuple (int 1) (unary - (float 2.5))))"),
+			("fn (a: Int, ...b): Int => a", "(fn ((a (ident Int)) ...b) (ident Int) (ident a))"),
+			("'outer: loop { break 'outer }", "(loop 'outer (block (break 'outer)))"),
+			("if let (a) = x a else { x = 1 }; a[..1]", "(if_let (a) (ident x) (ident a) (block (assign (ident x) (int 1))))\n(slice (ident a) (range _ (int 1)))"),
+			("{ x = 1, }", "(record (x (int 1)))"),
+			("type Id = Int?", "(type_alias Id (optional (ident Int)))"),
+			("", ""),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			assert_eq!(ast.dump(), dump, "{}", src);
+		}
+	}
+
+	#[test]
+	fn eof() {
+		for src in ["+", "1 +", "let", "fn (", "if", "x = ", "let x: "] {
+			let tokens = tokenize(src).unwrap();
+			let report = parse(src, &tokens).unwrap_err();
+			assert!(!report.errors().is_empty(), "{}", src);
+		}
+
+		let src = "x";
+		let tokens = tokenize(src).unwrap();
+		let mut parser = Parser::new(src, &tokens);
+		parser.next();
+		for _ in 0..3 {
+			assert_eq!(parser.next().kind, TT::Eof);
+		}
+		assert_eq!(parser.peek().kind, TT::Eof);
+		assert_eq!(parser.previous.unwrap().get(src), "x");
+	}
+
+	#[test]
+	fn named_fn() {
+		let src = "fn f (a) => a; fn (b) => b";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::Fn { name: Some(name), args, .. } = ast.get(&expr[0]) else { panic!() };
+		assert_eq!(name.get(src), "f");
+		assert_eq!(args[0].0.get(src), "a");
+		assert!(matches!(ast.get(&expr[1]), Node::Fn { name: None, .. }));
+		assert_eq!(ast.dump(), "(fn f (a) _ (ident a))\n(fn (b) _ (ident b))");
+	}
+
+	#[test]
+	fn attribute() {
+		let src = "#[inline] #[deprecated(\"use g\")] fn f (a) => a";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::Attribute { name, args, item, .. } = ast.get(&expr[0]) else { panic!() };
+		assert_eq!(name.get(src), "inline");
+		assert!(args.is_empty());
+		let Node::Attribute { name, args, item, .. } = ast.get(item) else { panic!() };
+		assert_eq!(name.get(src), "deprecated");
+		assert_eq!(args[0].get(src), "\"use g\"");
+		assert!(matches!(ast.get(item), Node::Fn { name: Some(_), .. }));
+		assert_eq!(ast.span(&expr[0]), Span::new(0, src.len() as u32));
+		assert_eq!(ast.dump(), "(attribute inline (attribute deprecated \"use g\" (fn f (a) _ (ident a))))");
+
+		for (src, message) in [
+			("#[inline] 1", "expected a declaration after an attribute, found '1'"),
+			("#[] let x = 1", "expected attribute name, found ']'"),
+			("#[a(+)] let x = 1", "expected attribute argument, found '+'"),
+			("#[a let x = 1", "expected ']', found 'let'"),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let report = parse(src, &tokens).unwrap_err();
+			assert_eq!(report.errors()[0].message, message, "{}", src);
+		}
+	}
+
+	#[test]
+	fn pretty_tree() {
+		let src = "1 + 1";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		assert_eq!(
+			ast.pretty_tree(),
+			concat!(
+				"Module\n",
+				"└─ Block\n",
+				"   └─ Binary +\n",
+				"      ├─ Integer 1\n",
+				"      └─ Integer 1\n",
+			),
+		);
+
+		let src = "let f = fn (a) => a * 2; f(1)";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		assert_eq!(
+			ast.pretty_tree(),
+			concat!(
+				"Module\n",
+				"└─ Block\n",
+				"   ├─ Let f\n",
+				"   │  └─ Fn a\n",
+				"   │     └─ Binary *\n",
+				"   │        ├─ Identifier a\n",
+				"   │        └─ Integer 2\n",
+				"   └─ Call\n",
+				"      ├─ Identifier f\n",
+				"      └─ Integer 1\n",
+			),
+		);
+	}
+
+	#[test]
+	fn reserved() {
+		for (src, message) in [
+			("for x", "'for' is not supported yet"),
+			("struct A", "'struct' is not supported yet"),
+			("let x = module", "'module' is not supported yet"),
+			("let for = 1", "expected identifier, found 'for'"),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let report = parse(src, &tokens).unwrap_err();
+			assert_eq!(report.errors()[0].message, message, "{}", src);
+		}
+	}
+
+	#[test]
+	fn node_at_offset() {
+		let src = "a + b * c";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+
+		let b = ast.node_at_offset(4).unwrap();
+		let Node::Identifier { name } = ast.get(&b) else { panic!() };
+		assert_eq!(name.get(src), "b");
+
+		let star = ast.node_at_offset(6).unwrap();
+		let Node::Binary { op, .. } = ast.get(&star) else { panic!() };
+		assert_eq!(op.kind, TT::Star);
+
+		// the gap before `+` is only inside the outer addition
+		let plus = ast.node_at_offset(1).unwrap();
+		assert!(matches!(ast.get(&plus), Node::Binary { op, .. } if op.kind == TT::Plus));
+
+		assert!(ast.node_at_offset(9).is_none());
+	}
+
+	#[test]
+	fn array() {
+		let src = "let xs: [Int?] = [1, nil, 3,]; []";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		assert_eq!(
+			ast.dump(),
+			"(let xs (array_type (optional (ident Int))) (array (int 1) (nil) (int 3)))\n(array)",
+		);
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		assert_eq!(ast.span(&expr[1]), Span::new(31, 33));
+
+		let src = "[1 2]";
+		let tokens = tokenize(src).unwrap();
+		let report = parse(src, &tokens).unwrap_err();
+		assert_eq!(report.errors()[0].message, "expected ',' or ']', found '2'");
+	}
+
+	#[test]
+	fn rollback() {
+		let src = "{ 1 + }";
+		let tokens = tokenize(src).unwrap();
+		let mut parser = Parser::new(src, &tokens);
+		let open = parser.next();
+		let checkpoint = parser.checkpoint();
+		parser.record(open);
+		assert!(!parser.report.ok());
+		assert!(!parser.nodes.is_empty());
+
+		parser.rollback(checkpoint);
+		assert!(parser.report.is_empty());
+		assert!(parser.nodes.is_empty() && parser.spans.is_empty());
+		assert_eq!(parser.peek().get(src), "1");
+		assert_eq!(parser.previous.unwrap().get(src), "{");
+	}
+
+	#[test]
+	fn trailing_block() {
+		for (src, dump) in [
+			("each(xs) { x }", "(call (ident each) (ident xs) (block (ident x)))"),
+			("each(xs) { x => x + 1 }", "(call (ident each) (ident xs) (fn (x) _ (block (binary + (ident x) (int 1)))))"),
+			("fold(xs, 0) { a, b => a; b }", "(call (ident fold) (ident xs) (int 0) (fn (a b) _ (block (ident a) (ident b))))"),
+			("f()\n{ x }", "(call (ident f))\n(block (ident x))"),
+			("if f(x) { 1 } else { 2 }", "(if (call (ident f) (ident x)) (block (int 1)) (block (int 2)))"),
+			("while (g() { x }) { y }", "(while (group (call (ident g) (block (ident x)))) (block (ident y)))"),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			assert_eq!(ast.dump(), dump, "{}", src);
+		}
+
+		let src = "each(xs) { x }";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		assert_eq!(ast.span(&expr[0]), Span::new(0, src.len() as u32));
+	}
+
+	#[test]
+	fn empty() {
+		for src in ["", "   ", "\n\t\r\n", "// just a comment", "/* a */ // b\n"] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+			let Node::Block { expr } = ast.get(root) else { panic!() };
+			assert!(expr.is_empty(), "{:?}", src);
+			assert_eq!(ast.dump(), "", "{:?}", src);
+
+			let tokens = tokenize_with(src, TokenizeOptions { keep_comments: true, ..Default::default() }).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			assert_eq!(ast.nodes.len(), 2, "{:?}", src);
+		}
+	}
+
+	#[test]
+	fn operator_table() {
+		let src = "1 - 2 - 3 * 4";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		assert_eq!(ast.dump(), "(binary - (binary - (int 1) (int 2)) (binary * (int 3) (int 4)))");
+
+		let mut operators = OperatorTable::default();
+		operators.set(TT::Minus, 2, Associativity::Right);
+		let ast = parse_with(src, &tokens, &operators).unwrap();
+		assert_eq!(ast.dump(), "(binary - (int 1) (binary - (int 2) (binary * (int 3) (int 4))))");
+
+		// `-` binding tighter than `*`
+		operators.set(TT::Minus, 4, Associativity::Left);
+		let ast = parse_with(src, &tokens, &operators).unwrap();
+		assert_eq!(ast.dump(), "(binary * (binary - (binary - (int 1) (int 2)) (int 3)) (int 4))");
+
+		let mut operators = OperatorTable::default();
+		operators.set(TT::Plus, 1, Associativity::None);
+		let src = "1 + 2 + 3";
+		let tokens = tokenize(src).unwrap();
+		let report = parse_with(src, &tokens, &operators).unwrap_err();
+		assert_eq!(report.errors()[0].message, "'+' cannot be chained, use parentheses to group it");
+	}
+
+	#[test]
+	fn break_value() {
+		for (src, dump) in [
+			("loop { break 1 + 2 }", "(loop (block (break (binary + (int 1) (int 2)))))"),
+			("'a: loop { break 'a x }", "(loop 'a (block (break 'a (ident x))))"),
+			("loop { break; 1 }", "(loop (block (break) (int 1)))"),
+			("loop { break\nx }", "(loop (block (break) (ident x)))"),
+			("loop { if x break else continue }", "(loop (block (if (ident x) (break) (continue))))"),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			assert_eq!(ast.dump(), dump, "{}", src);
+		}
+	}
+
+	#[test]
+	fn docs() {
+		let src = "/// the answer\n/// to everything\nlet x = 42\n// not a doc\nlet y = 1\n/// detached\n\nlet z = 2\n///bare\nexport fn f() => x";
+		let options = TokenizeOptions { keep_comments: true, ..Default::default() };
+		let tokens = tokenize_with(src, options).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+
+		let docs = doc_comments(&ast, &tokens);
+		assert_eq!(docs.len(), 2);
+		assert_eq!(docs[&expr[0]], "the answer\nto everything");
+		assert_eq!(docs[&expr[3]], "bare");
+	}
+
+	#[test]
+	fn else_if() {
+		let src = "if a 1 else if b 2 else 3";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		assert_eq!(ast.dump(), "(if (ident a) (int 1) (if (ident b) (int 2) (int 3)))");
+
+		// still in post-order, and the inner if spans to the end of the chain
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::If { else_branch: Some(inner), .. } = ast.get(&expr[0]) else { panic!() };
+		assert!(inner.0 < expr[0].0);
+		assert_eq!(ast.span(inner), Span::new(12, 25));
+		for (i, node) in ast.nodes.iter().enumerate() {
+			assert!(node.children().iter().all(|v| (v.0 as usize) < i));
+		}
+
+		let src = "if let x = y x else if z 1";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		assert_eq!(ast.dump(), "(if_let x (ident y) (ident x) (if (ident z) (int 1)))");
+	}
+
+	#[test]
+	fn free_vars() {
+		let free = |src: &str, bound: &[&str]| {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let bound = bound.iter().copied().collect::<HashSet<_>>();
+			let mut names = ast.free_vars(&ast.root, &bound).into_iter().map(|v| v.to_string()).collect::<Vec<_>>();
+			names.sort();
+			names
+		};
+
+		assert_eq!(free("fn (x) => x + y", &[]), vec!["y"]);
+		assert_eq!(free("fn (x) => x + y", &["y"]), Vec::<String>::new());
+		assert_eq!(free("let a = 1; let f = fn (x: T): U => x + a + b; f(c)", &["c"]), vec!["b"]);
+		assert_eq!(free("let x = x; { let y = 1 }; y", &[]), vec!["x", "y"]);
+		assert_eq!(free("g(); fn g () => h; export fn k () => g", &[]), vec!["h"]);
+		assert_eq!(free("if let (a, b) = t a + b else a", &[]), vec!["a", "t"]);
+		assert_eq!(free("const N = M; let v: [Int] = ([N]: [Int])", &[]), vec!["M"]);
+	}
+
+	#[test]
+	fn postfix_chain() {
+		let src = "a.b().c[0].d(1, 2)";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		assert_eq!(
+			ast.dump(),
+			"(call (field (index (field (call (field (ident a) b)) c) (int 0)) d) (int 1) (int 2))",
+		);
+
+		for (src, dump) in [
+			("t.0.1", "(field (field (ident t) 0) 1)"),
+			("f(x)?.y[1..]", "(slice (field (try (call (ident f) (ident x))) y) (range (int 1) _))"),
+			("-a.b", "(unary - (field (ident a) b))"),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			assert_eq!(ast.dump(), dump, "{}", src);
+		}
+
+		let src = "a.(b)";
+		let tokens = tokenize(src).unwrap();
+		let report = parse(src, &tokens).unwrap_err();
+		assert_eq!(report.errors()[0].message, "expected a field name after '.', found '('");
+	}
+
+	#[test]
+	fn enums() {
+		let src = "enum Opt { Some(Int), None }";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		assert_eq!(ast.dump(), "(enum Opt (Some (ident Int)) (None))");
+
+		let Node::Module { root } = ast.get(&ast.root) else { panic!() };
+		let Node::Block { expr } = ast.get(root) else { panic!() };
+		let Node::Enum { name, variants, .. } = ast.get(&expr[0]) else { panic!() };
+		assert_eq!(name.get(src), "Opt");
+		assert_eq!(variants.len(), 2);
+		assert_eq!(variants[0].0.get(src), "Some");
+		assert_eq!(variants[0].1.len(), 1);
+		assert_eq!(variants[1].0.get(src), "None");
+		assert!(variants[1].1.is_empty());
+
+		for (src, dump) in [
+			("enum Shape {\n\tRect(Float, Float),\n\tDot,\n}", "(enum Shape (Rect (ident Float) (ident Float)) (Dot))"),
+			("enum Never {}", "(enum Never)"),
+			("enum List { Cons(Int, List?), Empty() }", "(enum List (Cons (ident Int) (optional (ident List))) (Empty))"),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			assert_eq!(ast.dump(), dump, "{}", src);
+		}
+
+		let src = "enum Opt { Some(Int) None }";
+		let tokens = tokenize(src).unwrap();
+		let report = parse(src, &tokens).unwrap_err();
+		assert_eq!(report.errors()[0].message, "expected '}' after the variants of an enum, found 'None'");
+	}
+}
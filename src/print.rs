@@ -0,0 +1,450 @@
+
+use crate::{parse::{Ast, Node, NodeIndex, Pattern}, token::{keyword, split_suffix, Token, TT}};
+
+
+#[derive(Debug, Clone, Default)]
+pub struct PrintOptions {
+	/// print integers in decimal, so `0xFF` comes out as `255`. otherwise
+	/// literals keep the text they were written with
+	pub normalize_numbers: bool,
+}
+
+struct Print<'a> {
+	src: &'a str,
+	ast: &'a Ast<'a>,
+	options: &'a PrintOptions,
+	out: String,
+}
+impl<'a> Print<'a> {
+	fn text(&mut self, text: &str) {
+		self.out.push_str(text);
+	}
+
+	// an identifier, written raw if its name would otherwise be a keyword
+	fn name(&mut self, token: &Token) {
+		let name = token.get(self.src);
+		if token.kind == TT::Identifier && keyword(name).is_some() {
+			self.text("r#");
+		}
+		self.text(name);
+	}
+
+	fn list(&mut self, items: &[NodeIndex]) {
+		for (i, v) in items.iter().enumerate() {
+			if i > 0 {
+				self.text(", ");
+			}
+			self.node(v);
+		}
+	}
+
+	fn pattern(&mut self, pattern: &Pattern) {
+		match pattern {
+			Pattern::Identifier { name } => self.name(name),
+			Pattern::Tuple { items, .. } => {
+				self.text("(");
+				for (i, v) in items.iter().enumerate() {
+					if i > 0 {
+						self.text(", ");
+					}
+					self.pattern(v);
+				}
+				if items.len() == 1 {
+					self.text(",");
+				}
+				self.text(")");
+			}
+		}
+	}
+
+	fn label(&mut self, label: Option<&Token>) {
+		if let Some(label) = label {
+			self.text(label.get(self.src));
+			self.text(": ");
+		}
+	}
+
+	fn node(&mut self, index: &NodeIndex) {
+		let src = self.src;
+		match self.ast.get(index) {
+			// never in a tree that parsed successfully
+			Node::Error => self.text("<error>"),
+
+			Node::Module { root } => match self.ast.get(root) {
+				Node::Block { expr } => {
+					for (i, v) in expr.iter().enumerate() {
+						let start = self.out.len();
+						if i > 0 {
+							self.text("\n");
+						}
+						self.node(v);
+						// a statement starting like this would continue the one
+						// before it across the newline, as `f\n(1)` is a call
+						if i > 0 && self.out[start + 1..].starts_with(['(', '[', '{', '-']) {
+							self.out.insert(start, ';');
+						}
+					}
+				}
+				_ => self.node(root),
+			},
+			Node::Block { expr } => {
+				self.text("{");
+				for (i, v) in expr.iter().enumerate() {
+					self.text(if i > 0 { "; " } else { " " });
+					self.node(v);
+				}
+				self.text(if expr.is_empty() { "}" } else { " }" });
+			}
+
+			Node::Identifier { name } => self.name(name),
+			Node::Bool { value } => self.text(if *value { "true" } else { "false" }),
+			Node::Nil => self.text("nil"),
+			Node::Integer { value } => match self.ast.tokens.int_value(value) {
+				Some(number) if self.options.normalize_numbers => {
+					self.text(&number.to_string());
+					self.text(split_suffix(value.get(src)).1);
+				}
+				_ => self.text(value.get(src)),
+			},
+			Node::Float { value } => self.text(value.get(src)),
+
+			Node::Fn { name, args, variadic, ret, where_clause, expr } => {
+				self.text("fn ");
+				if let Some(name) = name {
+					self.name(name);
+					self.text(" ");
+				}
+				self.text("(");
+				for (i, (name, annotation)) in args.iter().enumerate() {
+					if i > 0 {
+						self.text(", ");
+					}
+					if *variadic && i + 1 == args.len() {
+						self.text("...");
+					}
+					self.name(name);
+					if let Some(annotation) = annotation {
+						self.text(": ");
+						self.node(annotation);
+					}
+				}
+				self.text(")");
+				if let Some(ret) = ret {
+					self.text(": ");
+					self.node(ret);
+				}
+				if let Some(where_clause) = where_clause {
+					self.text(" ");
+					self.node(where_clause);
+				}
+				self.text(" => ");
+				self.node(expr);
+			}
+			Node::Where { constraints, .. } => {
+				self.text("where ");
+				for (i, (name, bound)) in constraints.iter().enumerate() {
+					if i > 0 {
+						self.text(", ");
+					}
+					self.name(name);
+					self.text(": ");
+					self.node(bound);
+				}
+			}
+
+			Node::Group { expr } => {
+				self.text("(");
+				self.node(expr);
+				self.text(")");
+			}
+			Node::Record { fields } => {
+				self.text("{ ");
+				for (i, (name, value)) in fields.iter().enumerate() {
+					if i > 0 {
+						self.text(", ");
+					}
+					self.name(name);
+					self.text(" = ");
+					self.node(value);
+				}
+				self.text(" }");
+			}
+			Node::Tuple { items } => {
+				self.text("(");
+				self.list(items);
+				if items.len() == 1 {
+					self.text(",");
+				}
+				self.text(")");
+			}
+			Node::Array { items } => {
+				self.text("[");
+				self.list(items);
+				self.text("]");
+			}
+			Node::ArrayType { ty } => {
+				self.text("[");
+				self.node(ty);
+				self.text("]");
+			}
+			Node::Ascribe { expr, ty } => {
+				self.text("(");
+				self.node(expr);
+				self.text(": ");
+				self.node(ty);
+				self.text(")");
+			}
+
+			Node::Binary { left, op, right } => {
+				self.node(left);
+				self.text(" ");
+				self.text(op.get(src));
+				self.text(" ");
+				self.node(right);
+			}
+			Node::Unary { op, right } => {
+				self.text(op.get(src));
+				self.node(right);
+			}
+			Node::Call { expr, args, .. } => {
+				self.node(expr);
+				self.text("(");
+				self.list(args);
+				self.text(")");
+			}
+			Node::Field { expr, name, .. } => {
+				self.node(expr);
+				self.text(".");
+				self.name(name);
+			}
+			Node::Index { expr, index, .. } => {
+				self.node(expr);
+				self.text("[");
+				self.node(index);
+				self.text("]");
+			}
+			Node::Slice { expr, range, .. } => {
+				self.node(expr);
+				self.text("[");
+				self.node(range);
+				self.text("]");
+			}
+			Node::Range { start, op, end } => {
+				if let Some(start) = start {
+					self.node(start);
+				}
+				self.text(op.get(src));
+				if let Some(end) = end {
+					self.node(end);
+				}
+			}
+			Node::Try { expr, .. } => {
+				self.node(expr);
+				self.text("?");
+			}
+
+			// an `else if` chain is printed in a loop rather than recursively
+			Node::If { .. } => {
+				let mut current = Some(index.clone());
+				while let Some(next) = current.take() {
+					let Node::If { condition, then_branch, else_branch, .. } = self.ast.get(&next) else {
+						self.node(&next);
+						break;
+					};
+					self.text("if ");
+					self.node(condition);
+					self.text(" ");
+					self.node(then_branch);
+					if let Some(else_branch) = else_branch {
+						self.text(" else ");
+						current = Some(else_branch.clone());
+					}
+				}
+			}
+			Node::IfLet { pattern, expr, then_branch, else_branch, .. } => {
+				self.text("if let ");
+				self.pattern(pattern);
+				self.text(" = ");
+				self.node(expr);
+				self.text(" ");
+				self.node(then_branch);
+				if let Some(else_branch) = else_branch {
+					self.text(" else ");
+					self.node(else_branch);
+				}
+			}
+			Node::Loop { label, body, .. } => {
+				self.label(*label);
+				self.text("loop ");
+				self.node(body);
+			}
+			Node::While { label, condition, body, .. } => {
+				self.label(*label);
+				self.text("while ");
+				self.node(condition);
+				self.text(" ");
+				self.node(body);
+			}
+			Node::RepeatWhile { label, body, condition, .. } => {
+				self.label(*label);
+				self.text("repeat ");
+				self.node(body);
+				self.text(" while ");
+				self.node(condition);
+			}
+			Node::Break { op, label, .. } | Node::Continue { op, label } => {
+				self.text(op.get(src));
+				if let Some(label) = label {
+					self.text(" ");
+					self.text(label.get(src));
+				}
+				if let Node::Break { value: Some(value), .. } = self.ast.get(index) {
+					self.text(" ");
+					self.node(value);
+				}
+			}
+
+			Node::Optional { ty, .. } => {
+				self.node(ty);
+				self.text("?");
+			}
+			Node::Export { item, .. } => {
+				self.text("export ");
+				self.node(item);
+			}
+			Node::Attribute { name, args, item, .. } => {
+				self.text("#[");
+				self.name(name);
+				if !args.is_empty() {
+					self.text("(");
+					for (i, arg) in args.iter().enumerate() {
+						if i > 0 {
+							self.text(", ");
+						}
+						self.name(arg);
+					}
+					self.text(")");
+				}
+				self.text("] ");
+				self.node(item);
+			}
+			Node::TypeAlias { name, ty, .. } => {
+				self.text("type ");
+				self.name(name);
+				self.text(" = ");
+				self.node(ty);
+			}
+			Node::Enum { name, variants, .. } => {
+				self.text("enum ");
+				self.name(name);
+				self.text(" {");
+				for (i, (name, payload)) in variants.iter().enumerate() {
+					self.text(if i > 0 { ", " } else { " " });
+					self.name(name);
+					if !payload.is_empty() {
+						self.text("(");
+						self.list(payload);
+						self.text(")");
+					}
+				}
+				self.text(if variants.is_empty() { "}" } else { " }" });
+			}
+			Node::Assign { target, value, .. } => {
+				self.node(target);
+				self.text(" = ");
+				self.node(value);
+			}
+			Node::Const { name, ty, expr, .. } => {
+				self.text("const ");
+				self.name(name);
+				if let Some(ty) = ty {
+					self.text(": ");
+					self.node(ty);
+				}
+				self.text(" = ");
+				self.node(expr);
+			}
+			Node::Let { mutable, pattern, expr, annotation } => {
+				self.text(if *mutable { "mut " } else { "let " });
+				self.pattern(pattern);
+				if let Some(annotation) = annotation {
+					self.text(": ");
+					self.node(annotation);
+				}
+				self.text(" = ");
+				self.node(expr);
+			}
+		}
+	}
+}
+
+/// turns a tree back into source, with one top level statement per line,
+/// ending in `;` where the next line would otherwise continue it. comments
+/// and the original spacing are lost, but parsing the result gives back the
+/// same tree
+pub fn print(ast: &Ast, options: &PrintOptions) -> String {
+	let mut print = Print {
+		src: &ast.tokens.src,
+		ast,
+		options,
+		out: String::new(),
+	};
+	print.node(&ast.root);
+	print.out
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::{parse, print::{print, PrintOptions}, tokenize};
+
+	#[test]
+	fn round_trip() {
+		for src in [
+			"let x = 1 + 2 * (3 - -4)",
+			"mut (a, (b,)) = (1, (2,))",
+			"let f = fn (a: Int, ...b): Int? where T: U => if a > 0 a else { nil }",
+			"'outer: while true { repeat break 'outer while false; continue }",
+			"if let (a, b) = t a[1..] else ({ x = 1, y = 2 }: R)",
+			"export let x: Int = f(1, 2)?\nconst X = 0b10",
+			"type Id = Int??",
+			"let x = 'a: loop { break 'a 1 + 2 }",
+			"let xs: [Int?] = [1, nil, [2][0]]",
+			"let r#if = 1\nr#if + x",
+			"#[inline] #[since(1, \"a\")] export fn f (a) => a",
+			"fn fact (n) => if n < 2 1 else n * fact(n - 1)",
+			"let x = if a 1 else if b 2 else 3",
+			"a.b().c[0].d(1, t.0.1)",
+			"enum Opt { Some(Int, [Bool]?), None }\nenum Never {}",
+			"if a { 1 } else if let c = d { 2 } else if e { 3 }",
+			"let x = f;\n(1, 2)",
+			"let x = 1;\n-2",
+			"a;\n[1][0]",
+			"a\nb;\n{ c }",
+		] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let printed = print(&ast, &PrintOptions::default());
+			assert_eq!(printed, src);
+		}
+
+		// statements on one line, which a newline alone would join
+		for src in ["let x = f; (1, 2)", "let x = 1; -2", "x; [1]", "f(); { 1 }"] {
+			let tokens = tokenize(src).unwrap();
+			let ast = parse(src, &tokens).unwrap();
+			let printed = print(&ast, &PrintOptions::default());
+			let reparsed = tokenize(&printed).unwrap();
+			assert_eq!(parse(&printed, &reparsed).unwrap().dump(), ast.dump(), "{}", printed);
+		}
+	}
+
+	#[test]
+	fn normalize_numbers() {
+		let src = "let x = 0xFF + 0b11u8 + 1.5";
+		let tokens = tokenize(src).unwrap();
+		let ast = parse(src, &tokens).unwrap();
+		assert_eq!(print(&ast, &PrintOptions::default()), "let x = 0xFF + 0b11u8 + 1.5");
+		let options = PrintOptions { normalize_numbers: true };
+		assert_eq!(print(&ast, &options), "let x = 255 + 3u8 + 1.5");
+	}
+}
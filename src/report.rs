@@ -1,34 +1,235 @@
 
+use crate::token::{line_starts, location, Span};
+
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+	pub message: String,
+	pub span: Option<Span>,
+}
+impl std::fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self.span {
+			Some(span) => write!(f, "{} at {}..{}", self.message, span.start, span.end),
+			None => write!(f, "{}", self.message),
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+	/// how many columns a `\t` takes up, which should match the user's editor
+	pub tab_width: usize,
+	/// color severities and carets with ansi escapes. off by default, since
+	/// output may not be going to a terminal
+	pub color: bool,
+}
+impl Default for RenderOptions {
+	fn default() -> Self {
+		Self {
+			tab_width: 4,
+			color: false,
+		}
+	}
+}
+impl RenderOptions {
+	// wraps text in an ansi color, if colors are on
+	fn paint(&self, color: &str, text: &str) -> String {
+		if self.color {
+			format!("\x1b[{}m{}\x1b[0m", color, text)
+		} else {
+			text.to_string()
+		}
+	}
+}
+
+const RED: &str = "1;31";
+const YELLOW: &str = "1;33";
+
+#[derive(Clone)]
 pub struct Report {
 	fault: bool,
-	errors: Vec<String>,
-	warnings: Vec<String>,
+	// warnings are reported as errors
+	strict: bool,
+	errors: Vec<Diagnostic>,
+	warnings: Vec<Diagnostic>,
 }
 impl Report {
 	pub fn new() -> Self {
 		Self {
 			fault: false,
+			strict: false,
 			errors: Vec::new(),
 			warnings: Vec::new(),
 		}
 	}
 
+	/// when set, every warning is reported as an error instead, like
+	/// `-D warnings`. warnings already in the report are promoted right away
+	pub fn set_strict(&mut self, strict: bool) {
+		self.strict = strict;
+		if strict && !self.warnings.is_empty() {
+			self.errors.append(&mut self.warnings);
+			self.fault = true;
+		}
+	}
+
 	pub fn error(&mut self, msg: String) {
-		self.errors.push(msg);
+		self.errors.push(Diagnostic { message: msg, span: None });
+		self.fault = true;
+	}
+
+	pub fn error_at(&mut self, span: Span, msg: String) {
+		self.errors.push(Diagnostic { message: msg, span: Some(span) });
 		self.fault = true;
 	}
 
 	pub fn warn(&mut self, msg: String) {
-		self.warnings.push(msg);
+		if self.strict {
+			return self.error(msg);
+		}
+		self.warnings.push(Diagnostic { message: msg, span: None });
+	}
+
+	pub fn warn_at(&mut self, span: Span, msg: String) {
+		if self.strict {
+			return self.error_at(span, msg);
+		}
+		self.warnings.push(Diagnostic { message: msg, span: Some(span) });
 	}
 
 	pub fn ok(&self) -> bool {
 		!self.fault
 	}
+
+	/// whether there are no errors and no warnings
+	pub fn is_empty(&self) -> bool {
+		self.errors.is_empty() && self.warnings.is_empty()
+	}
+
+	/// `Ok(value)` if there were no errors, otherwise the report itself
+	pub fn into_result<T>(self, value: T) -> Result<T, Report> {
+		if self.ok() {
+			Ok(value)
+		} else {
+			Err(self)
+		}
+	}
+
+	pub fn errors(&self) -> &[Diagnostic] {
+		&self.errors
+	}
+
+	pub fn warnings(&self) -> &[Diagnostic] {
+		&self.warnings
+	}
+}
+impl Report {
+	/// renders every diagnostic for a terminal, quoting the source line of
+	/// each span with a caret underneath
+	pub fn render(&self, src: &str, options: &RenderOptions) -> String {
+		use std::fmt::Write;
+
+		let all = self.errors.iter().map(|v| ("error", RED, v))
+			.chain(self.warnings.iter().map(|v| ("warning", YELLOW, v)));
+
+		let starts = line_starts(src);
+		let mut out = String::new();
+		for (severity, color, diagnostic) in all {
+			writeln!(out, "{}: {}", options.paint(color, severity), diagnostic.message).unwrap();
+			if let Some(span) = diagnostic.span {
+				render_span(&mut out, src, &starts, span, color, options);
+			}
+		}
+		out
+	}
+}
+
+fn render_span(out: &mut String, src: &str, starts: &[u32], span: Span, color: &str, options: &RenderOptions) {
+	use std::fmt::Write;
+
+	let start = (span.start as usize).min(src.len());
+	let line = location(src, starts, start as u32).0 as usize;
+	let line_start = starts[line - 1] as usize;
+	let line_end = starts.get(line).map_or(src.len(), |v| *v as usize);
+	let text = src[line_start..line_end].trim_end_matches(['\n', '\r', '\u{2028}', '\u{2029}']);
+
+	let width = |text: &str| -> usize {
+		text.chars().map(|c| if c == '\t' { options.tab_width } else { 1 }).sum()
+	};
+	let col = width(&src[line_start..start]);
+	let end = (span.end as usize).clamp(start, line_start + text.len());
+	let len = width(&src[start..end]).max(1);
+
+	let gutter = " ".repeat(line.to_string().len());
+	writeln!(out, "{}--> {}:{}", gutter, line, col + 1).unwrap();
+	writeln!(out, "{} |", gutter).unwrap();
+	writeln!(out, "{} | {}", line, text.replace('\t', &" ".repeat(options.tab_width))).unwrap();
+	writeln!(out, "{} | {}{}", gutter, " ".repeat(col), options.paint(color, &"^".repeat(len))).unwrap();
+}
+
+#[cfg(feature = "serde")]
+impl Report {
+	/// serializes every diagnostic into a json array of
+	/// `{ severity, message, start, end, line, col }` objects. diagnostics
+	/// without a span leave out the position fields
+	pub fn to_json(&self, src: &str) -> String {
+		use std::fmt::Write;
+
+		let all = self.errors.iter().map(|v| ("error", v))
+			.chain(self.warnings.iter().map(|v| ("warning", v)));
+
+		let starts = line_starts(src);
+		let mut out = String::from("[");
+		for (i, (severity, diagnostic)) in all.enumerate() {
+			if i > 0 {
+				out.push(',');
+			}
+			write!(out, "{{\"severity\":\"{}\",\"message\":", severity).unwrap();
+			json_string(&mut out, &diagnostic.message);
+			if let Some(span) = diagnostic.span {
+				let (line, col) = location(src, &starts, span.start);
+				write!(
+					out, ",\"start\":{},\"end\":{},\"line\":{},\"col\":{}",
+					span.start, span.end, line, col,
+				).unwrap();
+			}
+			out.push('}');
+		}
+		out.push(']');
+		out
+	}
+}
+
+#[cfg(feature = "serde")]
+fn json_string(out: &mut String, value: &str) {
+	use std::fmt::Write;
+
+	out.push('"');
+	for c in value.chars() {
+		match c {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\t' => out.push_str("\\t"),
+			c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+			c => out.push(c),
+		}
+	}
+	out.push('"');
+}
+
+impl Default for Report {
+	fn default() -> Self {
+		Self::new()
+	}
 }
 impl std::fmt::Display for Report {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		write!(f, "report! {{ errors: {:?}, warnings: {:?} }}", self.errors, self.warnings)
+		let errors = self.errors.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+		let warnings = self.warnings.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+		write!(f, "report! {{ errors: {:?}, warnings: {:?} }}", errors, warnings)
 	}
 }
 impl std::fmt::Debug for Report {
@@ -38,3 +239,100 @@ impl std::fmt::Debug for Report {
 }
 impl std::error::Error for Report {}
 
+
+#[cfg(test)]
+mod test {
+	use crate::{report::{RenderOptions, Report}, token::Span};
+
+	#[test]
+	fn into_result() {
+		let mut report = Report::new();
+		assert!(report.is_empty());
+
+		report.warn("unused".to_string());
+		assert!(!report.is_empty());
+		assert_eq!(report.into_result(1).unwrap(), 1);
+
+		let mut report = Report::new();
+		report.error("bad".to_string());
+		let report = report.into_result(1).unwrap_err();
+		assert_eq!(report.errors()[0].message, "bad");
+	}
+
+	#[test]
+	fn strict() {
+		let mut report = Report::new();
+		report.warn("unused".to_string());
+		assert!(report.clone().into_result(()).is_ok());
+
+		report.set_strict(true);
+		report.warn("shadowed".to_string());
+		assert!(report.warnings().is_empty());
+		let messages = report.errors().iter().map(|v| v.message.as_str()).collect::<Vec<_>>();
+		assert_eq!(messages, vec!["unused", "shadowed"]);
+		assert!(report.into_result(()).is_err());
+	}
+
+	#[test]
+	fn render() {
+		let src = "let x = 1\nlet = 2";
+		let mut report = Report::new();
+		report.error_at(Span::new(14, 15), "expected identifier, found '='".to_string());
+		report.warn("unused".to_string());
+		assert_eq!(
+			report.render(src, &RenderOptions::default()),
+			concat!(
+				"error: expected identifier, found '='\n",
+				" --> 2:5\n",
+				"  |\n",
+				"2 | let = 2\n",
+				"  |     ^\n",
+				"warning: unused\n",
+			),
+		);
+	}
+
+	#[test]
+	fn render_tabs() {
+		let src = "\tx";
+		let mut report = Report::new();
+		report.error_at(Span::new(1, 2), "here".to_string());
+		for (tab_width, caret) in [(4, "  |     ^\n"), (8, "  |         ^\n")] {
+			let rendered = report.render(src, &RenderOptions { tab_width, ..Default::default() });
+			assert!(rendered.ends_with(caret), "{}", rendered);
+		}
+	}
+
+	#[test]
+	fn render_color() {
+		let src = "x";
+		let mut report = Report::new();
+		report.error_at(Span::new(0, 1), "bad".to_string());
+		report.warn("odd".to_string());
+
+		let plain = report.render(src, &RenderOptions::default());
+		assert!(!plain.contains('\x1b'));
+
+		let colored = report.render(src, &RenderOptions { color: true, ..Default::default() });
+		assert!(colored.starts_with("\x1b[1;31merror\x1b[0m: bad\n"));
+		assert!(colored.contains("| \x1b[1;31m^\x1b[0m\n"));
+		assert!(colored.ends_with("\x1b[1;33mwarning\x1b[0m: odd\n"));
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn json() {
+
+		let src = "let x = 1\nlet = 2";
+		let mut report = Report::new();
+		report.error_at(Span::new(14, 15), "expected identifier, found '='".to_string());
+		report.warn("unused \"x\"".to_string());
+		assert_eq!(
+			report.to_json(src),
+			concat!(
+				r#"[{"severity":"error","message":"expected identifier, found '='","start":14,"end":15,"line":2,"col":5},"#,
+				r#"{"severity":"warning","message":"unused \"x\""}]"#,
+			),
+		);
+	}
+}
@@ -1,25 +1,35 @@
 
 use crate::report::Report;
 
-use std::marker::PhantomData;
+use std::{borrow::Cow, collections::HashMap, iter::Peekable, ops::Range, str::Chars};
 
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TT {
 	Eof,
 
 	True,
 	False,
+	Nil,
 	Identifier,
 	Integer,
 	Float,
+	Label, // 'name
+	String, // "..."
+	RawString, // r"..." or r#"..."#
+	Char, // '...'
+
+	LineComment, // // ...
+	BlockComment, // /* ... */
 	
 	Plus, // +
 	Minus, // -
 	Star, // *
 	Slash, // /
+	Percent, // %
 
 	Bang, // !
+	Question, // ?
 
 	Equal, // =
 	EqualEqual, // ==
@@ -37,12 +47,16 @@ pub enum TT {
 	RBrace, // }
 
 	Dot, // .
+	DotDot, // ..
+	DotDotDot, // ...
 	Comma, // ,
+	Hash, // #
 
 	Colon, // :
 	SemiColon, // ;
 
 	Let, // let
+	Const, // const
 	Mut, // mut
 
 	If, // if
@@ -50,25 +64,186 @@ pub enum TT {
 	For, // for
 	While, // while
 	Loop, // loop
+	Repeat, // repeat
+	Break, // break
+	Continue, // continue
 
 	Export, // export
+	Type, // type
+	Enum, // enum
+	Where, // where
 
 	Struct, // struct
 	Module, // module
 	Fn, // fn
 	EqualGreater, // =>
 }
+impl TT {
+	pub fn is_literal(self) -> bool {
+		matches!(self,
+			TT::True | TT::False | TT::Nil
+			| TT::Integer | TT::Float
+			| TT::String | TT::RawString | TT::Char
+		)
+	}
+
+	pub fn is_keyword(self) -> bool {
+		matches!(self,
+			TT::True | TT::False | TT::Nil
+			| TT::Let | TT::Const | TT::Mut
+			| TT::If | TT::Else | TT::For | TT::While | TT::Loop | TT::Repeat
+			| TT::Break | TT::Continue
+			| TT::Export | TT::Type | TT::Enum | TT::Where | TT::Struct | TT::Module | TT::Fn
+		)
+	}
+
+	pub fn is_binary_op(self) -> bool {
+		matches!(self,
+			TT::Plus | TT::Minus | TT::Star | TT::Slash | TT::Percent
+			| TT::EqualEqual | TT::BangEqual
+			| TT::Lesser | TT::Greater | TT::LesserEqual | TT::GreaterEqual
+		)
+	}
+
+	pub fn is_unary_op(self) -> bool {
+		matches!(self, TT::Minus | TT::Bang)
+	}
+
+	/// the fixed spelling of a token, or `None` for tokens like identifiers
+	/// and literals whose text varies
+	pub fn symbol(self) -> Option<&'static str> {
+		let symbol = match self {
+			TT::Plus => "+",
+			TT::Minus => "-",
+			TT::Star => "*",
+			TT::Slash => "/",
+			TT::Percent => "%",
+			TT::Bang => "!",
+			TT::Question => "?",
+			TT::Equal => "=",
+			TT::EqualEqual => "==",
+			TT::BangEqual => "!=",
+			TT::Lesser => "<",
+			TT::Greater => ">",
+			TT::LesserEqual => "<=",
+			TT::GreaterEqual => ">=",
+			TT::LParen => "(",
+			TT::RParen => ")",
+			TT::LBracket => "[",
+			TT::RBracket => "]",
+			TT::LBrace => "{",
+			TT::RBrace => "}",
+			TT::Dot => ".",
+			TT::DotDot => "..",
+			TT::DotDotDot => "...",
+			TT::Comma => ",",
+			TT::Hash => "#",
+			TT::Colon => ":",
+			TT::SemiColon => ";",
+			TT::EqualGreater => "=>",
+			TT::True => "true",
+			TT::False => "false",
+			TT::Nil => "nil",
+			TT::Let => "let",
+			TT::Const => "const",
+			TT::Mut => "mut",
+			TT::If => "if",
+			TT::Else => "else",
+			TT::For => "for",
+			TT::While => "while",
+			TT::Loop => "loop",
+			TT::Repeat => "repeat",
+			TT::Break => "break",
+			TT::Continue => "continue",
+			TT::Export => "export",
+			TT::Type => "type",
+			TT::Enum => "enum",
+			TT::Where => "where",
+			TT::Struct => "struct",
+			TT::Module => "module",
+			TT::Fn => "fn",
+			_ => return None,
+		};
+		Some(symbol)
+	}
+
+	/// tokens that carry no meaning for the parser, such as comments
+	pub fn is_trivia(self) -> bool {
+		matches!(self, TT::LineComment | TT::BlockComment)
+	}
+}
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+	pub start: u32,
+	pub end: u32,
+}
+impl Span {
+	pub fn new(start: u32, end: u32) -> Self {
+		Self {
+			start,
+			end,
+		}
+	}
+}
+
+#[derive(Clone, PartialEq, Eq)]
 pub struct Token {
 	pub kind: TT,
 	src: (u32, u32),
+	pos: (u32, u32),
+	// an identifier's name in NFC, when that isn't how the source spells it
+	#[cfg(feature = "unicode-normalization")]
+	name: Option<Box<str>>,
 }
 impl Token {
-	pub fn new(kind: TT, src: (u32, u32)) -> Self {
+	pub const fn new(kind: TT, src: (u32, u32), pos: (u32, u32)) -> Self {
 		Self {
 			kind,
 			src,
+			pos,
+			#[cfg(feature = "unicode-normalization")]
+			name: None,
+		}
+	}
+
+	/// the 1-based line the token starts on
+	pub fn line(&self) -> u32 {
+		self.pos.0
+	}
+
+	/// the 1-based column, in chars, the token starts at
+	pub fn col(&self) -> u32 {
+		self.pos.1
+	}
+
+	pub fn span(&self) -> Span {
+		Span::new(self.src.0, self.src.1)
+	}
+
+	/// the token's text. a raw identifier like `r#if` gives just its name,
+	/// so it's the same name as a plain `if` would be
+	#[cfg(not(feature = "unicode-normalization"))]
+	pub fn get<'a>(&self, src: &'a str) -> &'a str {
+		self.text(src)
+	}
+
+	/// the token's text. a raw identifier like `r#if` gives just its name,
+	/// so it's the same name as a plain `if` would be. identifiers come out
+	/// in NFC, which may be stored in the token rather than the source
+	#[cfg(feature = "unicode-normalization")]
+	pub fn get<'a>(&'a self, src: &'a str) -> &'a str {
+		match &self.name {
+			Some(name) => name,
+			None => self.text(src),
+		}
+	}
+
+	fn text<'a>(&self, src: &'a str) -> &'a str {
+		let text = &src[self.src.0 as usize..self.src.1 as usize];
+		match self.kind {
+			TT::Identifier => identifier_name(text),
+			_ => text,
 		}
 	}
 }
@@ -85,62 +260,476 @@ impl std::fmt::Debug for Token {
 
 #[derive(Debug)]
 pub struct TokenStream<'a> {
-	pub src: &'a str,
+	pub src: Cow<'a, str>,
 	pub tokens: Vec<Token>,
+	line_starts: Vec<u32>,
 }
 impl<'a> TokenStream<'a> {
-	pub fn new(src: &'a str, tokens: Vec<Token>) -> Self {
+	pub fn new(src: impl Into<Cow<'a, str>>, tokens: Vec<Token>) -> Self {
+		let src = src.into();
 		Self {
+			line_starts: line_starts(&src),
 			src,
 			tokens,
 		}
 	}
 
+	/// the byte offset each line of the source starts at, see [`line_starts`]
+	pub fn line_starts(&self) -> &[u32] {
+		&self.line_starts
+	}
+
+	/// the 1-based line and column, in chars, of a byte offset into the
+	/// source. it agrees with [`Token::line`] and [`Token::col`]
+	pub fn location(&self, offset: u32) -> (u32, u32) {
+		location(&self.src, &self.line_starts, offset)
+	}
+
 	pub fn get(&self, index: usize) -> &Token {
 		&self.tokens[index]
 	}
 
+	pub fn iter(&self) -> std::slice::Iter<'_, Token> {
+		self.tokens.iter()
+	}
+
+	/// how many times each token kind appears, including the trailing [`TT::Eof`]
+	pub fn kind_counts(&self) -> HashMap<TT, usize> {
+		let mut counts = HashMap::new();
+		for token in &self.tokens {
+			*counts.entry(token.kind).or_insert(0) += 1;
+		}
+		counts
+	}
+
+	/// like [`Token::get`], so raw identifiers come without their `r#`
+	#[cfg(not(feature = "unicode-normalization"))]
 	pub fn str_from(&self, token: &Token) -> &str {
-		&self.src[token.src.0 as usize..token.src.1 as usize]
+		token.get(&self.src)
+	}
+
+	/// like [`Token::get`], so raw identifiers come without their `r#` and
+	/// in NFC
+	#[cfg(feature = "unicode-normalization")]
+	pub fn str_from<'b>(&'b self, token: &'b Token) -> &'b str {
+		token.get(&self.src)
+	}
+
+	/// the source from the start of `start` to the end of `end`, including
+	/// anything between them like whitespace and comments.
+	///
+	/// panics if `end` finishes before `start` begins
+	pub fn slice_between(&self, start: &Token, end: &Token) -> &str {
+		assert!(
+			start.src.0 <= end.src.1,
+			"slice_between: {} doesn't precede {}", start, end,
+		);
+		&self.src[start.src.0 as usize..end.src.1 as usize]
+	}
+
+	/// the value of a [`TT::Integer`] token, which may be written with a
+	/// `0x`, `0o`, or `0b` prefix. `None` if it doesn't fit in a `u128`
+	pub fn int_value(&self, token: &Token) -> Option<u128> {
+		if token.kind != TT::Integer {
+			return None;
+		}
+		int_value(self.str_from(token))
 	}
+
+	/// the value of a [`TT::Float`] token
+	pub fn float_value(&self, token: &Token) -> Option<f64> {
+		if token.kind != TT::Float {
+			return None;
+		}
+		float_value(self.str_from(token))
+	}
+
+	/// the decoded contents of a [`TT::String`] token
+	pub fn string_value(&self, token: &Token) -> Option<String> {
+		if token.kind != TT::String {
+			return None;
+		}
+		let text = self.str_from(token);
+		unescape(&text[1..text.len() - 1]).ok()
+	}
+
+	/// the contents of a [`TT::RawString`] token, exactly as written
+	pub fn raw_string_value(&self, token: &Token) -> Option<&str> {
+		if token.kind != TT::RawString {
+			return None;
+		}
+		let text = &self.src[token.src.0 as usize + 1..token.src.1 as usize];
+		let hashes = text.len() - text.trim_start_matches('#').len();
+		Some(&text[hashes + 1..text.len() - hashes - 1])
+	}
+
+	/// the decoded contents of a [`TT::Char`] token
+	pub fn char_value(&self, token: &Token) -> Option<char> {
+		if token.kind != TT::Char {
+			return None;
+		}
+		let text = self.str_from(token);
+		unescape(&text[1..text.len() - 1]).ok()?.chars().next()
+	}
+}
+
+
+/// decodes the escape sequences in the body of a string or char literal.
+/// on failure, returns the byte range of the offending escape within `text`
+pub fn unescape(text: &str) -> Result<String, (Range<usize>, String)> {
+	let mut out = String::with_capacity(text.len());
+	let mut iter = text.char_indices().peekable();
+
+	while let Some((start, c)) = iter.next() {
+		if c != '\\' {
+			out.push(c);
+			continue;
+		}
+
+		let escape = iter.next().map(|(_, c)| c);
+		match escape {
+			Some('n') => out.push('\n'),
+			Some('t') => out.push('\t'),
+			Some('r') => out.push('\r'),
+			Some('0') => out.push('\0'),
+			Some('\\') => out.push('\\'),
+			Some('"') => out.push('"'),
+			Some('\'') => out.push('\''),
+
+			Some('x') => {
+				let mut digits = String::new();
+				while digits.len() < 2 {
+					let Some((_, c)) = iter.next_if(|(_, c)| c.is_ascii_hexdigit()) else {
+						break;
+					};
+					digits.push(c);
+				}
+				let end = iter.peek().map_or(text.len(), |(i, _)| *i);
+
+				if digits.len() != 2 {
+					return Err((start..end, "expected two hex digits in '\\x' escape".to_string()));
+				}
+				let value = u8::from_str_radix(&digits, 16).unwrap();
+				if value > 0x7f {
+					return Err((start..end, "'\\x' escape must be in the range 00..=7f".to_string()));
+				}
+				out.push(value as char);
+			}
+
+			Some('u') => {
+				if iter.next_if(|(_, c)| *c == '{').is_none() {
+					let end = iter.peek().map_or(text.len(), |(i, _)| *i);
+					return Err((start..end, "expected '{' in '\\u' escape".to_string()));
+				}
+				let mut digits = String::new();
+				while let Some((_, c)) = iter.next_if(|(_, c)| c.is_ascii_hexdigit()) {
+					digits.push(c);
+				}
+				let closed = iter.next_if(|(_, c)| *c == '}').is_some();
+				let end = iter.peek().map_or(text.len(), |(i, _)| *i);
+
+				if !closed {
+					return Err((start..end, "expected '}' in '\\u' escape".to_string()));
+				}
+				if digits.is_empty() || digits.len() > 6 {
+					return Err((start..end, "'\\u' escape must have 1 to 6 hex digits".to_string()));
+				}
+				let value = u32::from_str_radix(&digits, 16).unwrap();
+				match char::from_u32(value) {
+					Some(c) => out.push(c),
+					None => return Err((start..end, format!("invalid unicode code point {:x}", value))),
+				}
+			}
+
+			Some(c) => {
+				let end = start + 1 + c.len_utf8();
+				return Err((start..end, format!("unknown escape '\\{}'", c)));
+			}
+			None => {
+				return Err((start..text.len(), "unfinished escape".to_string()));
+			}
+		}
+	}
+
+	Ok(out)
+}
+
+
+#[derive(Debug, Clone, Default)]
+pub struct TokenizeOptions {
+	/// when set, identifiers longer than this many chars are rejected
+	pub max_identifier_len: Option<usize>,
+	/// emit comments as [`TT::LineComment`] and [`TT::BlockComment`] tokens
+	/// instead of skipping them
+	pub keep_comments: bool,
 }
 
 
 struct Tokenize<'a> {
 	src: &'a str,
+	options: TokenizeOptions,
 	tokens: Vec<Token>,
+	// when unset, tokens are only counted and never stored
+	keep: bool,
+	count: usize,
+	// kind of the last non-trivia token, which is all `after_operand` needs
+	last: Option<TT>,
 	report: Report,
 	start: usize,
 	current: usize,
+	line: u32,
+	col: u32,
+	// line and column of `start`
+	pos: (u32, u32),
+	// the longest source, in bytes, whose offsets fit in a span
+	max_len: usize,
 }
 impl<'a> Tokenize<'a> {
-	fn new(src: &'a str) -> Self {
+	fn new(src: &'a str, options: TokenizeOptions, tokens: Vec<Token>) -> Self {
 		Self {
 			src,
-			tokens: Vec::new(),
+			options,
+			tokens,
+			keep: true,
+			count: 0,
+			last: None,
+			max_len: u32::MAX as usize,
 			report: Report::new(),
 			start: 0,
 			current: 0,
+			line: 1,
+			col: 1,
+			pos: (1, 1),
+		}
+	}
+
+	// moves past `c`. `\r\n`, `\n`, a lone `\r`, and the unicode line and
+	// paragraph separators each count as a single line break
+	fn bump(&mut self, c: char, next: Option<&char>) {
+		self.current += c.len_utf8();
+		match c {
+			'\r' if next == Some(&'\n') => (),
+			'\n' | '\r' | '\u{2028}' | '\u{2029}' => {
+				self.line += 1;
+				self.col = 1;
+			}
+			_ => self.col += 1,
 		}
 	}
 
-	fn advance(&mut self, iter: &mut impl Iterator<Item = char>) {
-		iter.next();
-		self.current += 1;
+	fn advance(&mut self, iter: &mut Peekable<Chars>) {
+		if let Some(c) = iter.next() {
+			self.bump(c, iter.peek());
+		}
+	}
+
+	fn digits(&mut self, iter: &mut Peekable<Chars>) {
+		while iter.peek().is_some_and(|c| c.is_numeric()) {
+			self.advance(iter);
+		}
+	}
+
+	// a type suffix right after a number, as in `10i64`, then the literal's
+	// token. anything else glued to a number, like `123abc`, is almost
+	// always a typo, so it's an error rather than a number and a name
+	fn suffix(&mut self, iter: &mut Peekable<Chars>, kind: TT) {
+		let start = self.current;
+		if iter.peek().is_some_and(|c| c.is_alphabetic()) {
+			while iter.peek().is_some_and(|c| c.is_alphanumeric()) {
+				self.advance(iter);
+			}
+		}
+		let suffix = &self.src[start..self.current];
+		if suffix.is_empty() || SUFFIXES.contains(&suffix) {
+			self.add(kind);
+		} else {
+			self.report.error_at(self.span(), "invalid number literal".to_string());
+		}
+	}
+
+	// the radix of an integer starting with `0x`, `0o`, or `0b`, where `c`
+	// is the `0` just scanned. the prefix only counts if a digit follows it
+	fn radix_prefix(&self, c: char, iter: &Peekable<Chars>) -> Option<u32> {
+		if c != '0' {
+			return None;
+		}
+		let mut ahead = iter.clone();
+		let radix = match ahead.next()? {
+			'x' => 16,
+			'o' => 8,
+			'b' => 2,
+			_ => return None,
+		};
+		ahead.peek().is_some_and(|c| c.is_digit(radix)).then_some(radix)
 	}
 
-	fn build(mut self) -> Result<TokenStream<'a>, Report> {
+	fn last(&self) -> Option<TT> {
+		self.last
+	}
+
+	// whether the last token could end an operand, so a `.` after it is
+	// accessing something, as in `t.0`, rather than starting a float
+	fn after_operand(&self) -> bool {
+		matches!(
+			self.last(),
+			Some(TT::Identifier | TT::Integer | TT::Float | TT::RParen | TT::RBracket | TT::RBrace),
+		)
+	}
+
+	// scans the rest of a quoted literal, up to and including the closing
+	// quote. returns false if the input ran out first
+	fn quoted(&mut self, iter: &mut Peekable<Chars>, quote: char) -> bool {
+		while let Some(&c) = iter.peek() {
+			self.advance(iter);
+			if c == quote {
+				return true;
+			} else if c == '\\' {
+				self.advance(iter);
+			}
+		}
+		false
+	}
+
+	// whether the `'` just scanned starts a label like `'outer` rather than a
+	// char literal like `'a'`
+	fn label_prefix(&self, iter: &Peekable<Chars>) -> bool {
+		let mut ahead = iter.clone();
+		if ahead.next_if(|c| c.is_alphabetic() || *c == '_').is_none() {
+			return false;
+		}
+		while ahead.next_if(|c| c.is_alphanumeric() || *c == '_').is_some() {}
+		ahead.peek() != Some(&'\'')
+	}
+
+	// whether the `r` just scanned starts a raw identifier, as in `r#if`
+	fn raw_identifier_prefix(&self, iter: &Peekable<Chars>) -> bool {
+		let mut ahead = iter.clone();
+		ahead.next() == Some('#') && ahead.peek().is_some_and(|c| c.is_alphabetic())
+	}
+
+	// whether the `r` just scanned starts a raw string, as in `r"` or `r#"`
+	fn raw_prefix(&self, iter: &Peekable<Chars>) -> bool {
+		let mut ahead = iter.clone();
+		while ahead.next_if_eq(&'#').is_some() {}
+		ahead.peek() == Some(&'"')
+	}
+
+	fn raw_string(&mut self, iter: &mut Peekable<Chars>) {
+		let mut hashes = 0;
+		while iter.peek() == Some(&'#') {
+			self.advance(iter);
+			hashes += 1;
+		}
+		self.advance(iter);
+
+		while let Some(&c) = iter.peek() {
+			self.advance(iter);
+			if c != '"' {
+				continue;
+			}
+			let mut ahead = iter.clone();
+			if (0..hashes).all(|_| ahead.next() == Some('#')) {
+				for _ in 0..hashes {
+					self.advance(iter);
+				}
+				self.add(TT::RawString);
+				return;
+			}
+		}
+
+		// `r`, the hashes, and the quote
+		self.report.error_at(self.opener(hashes + 2), "unterminated raw string started here".to_string());
+	}
+
+	fn block_comment(&mut self, iter: &mut Peekable<Chars>) {
+		while let Some(c) = iter.next() {
+			self.bump(c, iter.peek());
+			if c == '*' && iter.peek() == Some(&'/') {
+				self.advance(iter);
+				self.comment(TT::BlockComment);
+				return;
+			}
+		}
+		self.report.error_at(self.opener(2), "unterminated block comment started here".to_string());
+	}
+
+	fn comment(&mut self, kind: TT) {
+		if self.options.keep_comments {
+			self.add(kind);
+		}
+	}
+
+	// validates a scanned literal's escapes, returning its decoded contents
+	fn escapes(&mut self) -> Option<String> {
+		let body = &self.src[self.start + 1..self.current - 1];
+		match unescape(body) {
+			Ok(value) => Some(value),
+			Err((range, msg)) => {
+				let offset = self.start + 1;
+				self.report.error_at(
+					Span::new((offset + range.start) as u32, (offset + range.end) as u32),
+					msg,
+				);
+				None
+			}
+		}
+	}
+
+	fn build(mut self) -> (Vec<Token>, Report) {
+		self.scan();
+		(self.tokens, self.report)
+	}
+
+	fn scan(&mut self) {
+		if self.src.len() > self.max_len {
+			self.report.error(format!(
+				"source too large: {} bytes, the maximum is {}",
+				self.src.len(), self.max_len,
+			));
+			self.eof();
+			return;
+		}
+
 		let mut iter = self.src.chars().peekable();
 
+		// a byte order mark isn't part of the program. it still counts toward
+		// offsets, but not columns
+		if iter.next_if_eq(&'\u{FEFF}').is_some() {
+			self.current += '\u{FEFF}'.len_utf8();
+		}
+
+		// a shebang line, as in `#!/usr/bin/env myano`, is for the shell. it's
+		// only counts at the very start, after any byte order mark. anywhere
+		// else `#!` is tokenized as usual
+		if self.src[self.current..].starts_with("#!") {
+			while let Some(c) = iter.next_if(|c| !matches!(c, '\n' | '\r' | '\u{2028}' | '\u{2029}')) {
+				self.bump(c, iter.peek());
+			}
+		}
+
 		while let Some(c) = iter.next() {
 			self.start = self.current;
-			self.current += 1;
+			self.pos = (self.line, self.col);
+			self.bump(c, iter.peek());
 			
 			match c {
 				'+' => self.add(TT::Plus),
 				'-' => self.add(TT::Minus),
 				'*' => self.add(TT::Star),
-				'/' => self.add(TT::Slash),
+				'/' => match iter.peek() {
+					Some('/') => {
+						while iter.peek().is_some_and(|c| !matches!(c, '\n' | '\r' | '\u{2028}' | '\u{2029}')) {
+							self.advance(&mut iter);
+						}
+						self.comment(TT::LineComment);
+					}
+					Some('*') => {
+						self.advance(&mut iter);
+						self.block_comment(&mut iter);
+					}
+					_ => self.add(TT::Slash),
+				},
+				'%' => self.add(TT::Percent),
 
 				'=' => match iter.peek() {
 					Some('=') => {
@@ -153,6 +742,27 @@ impl<'a> Tokenize<'a> {
 					},
 					_ => self.add(TT::Equal),
 				},
+				'!' => match iter.peek() {
+					Some('=') => {
+						self.advance(&mut iter);
+						self.add(TT::BangEqual);
+					},
+					_ => self.add(TT::Bang),
+				},
+				'<' => match iter.peek() {
+					Some('=') => {
+						self.advance(&mut iter);
+						self.add(TT::LesserEqual);
+					},
+					_ => self.add(TT::Lesser),
+				},
+				'>' => match iter.peek() {
+					Some('=') => {
+						self.advance(&mut iter);
+						self.add(TT::GreaterEqual);
+					},
+					_ => self.add(TT::Greater),
+				},
 
 				'(' => self.add(TT::LParen),
 				')' => self.add(TT::RParen),
@@ -161,53 +771,113 @@ impl<'a> Tokenize<'a> {
 				'{' => self.add(TT::LBrace),
 				'}' => self.add(TT::RBrace),
 
-				'.' => self.add(TT::Dot),
+				'.' => match iter.peek() {
+					Some('.') => {
+						self.advance(&mut iter);
+						if iter.peek() == Some(&'.') {
+							self.advance(&mut iter);
+							self.add(TT::DotDotDot);
+						} else {
+							self.add(TT::DotDot);
+						}
+					},
+					// a float needs a digit on at least one side of the `.`, so
+					// `.5` is a float but a lone `.` isn't
+					Some(c) if c.is_numeric() && !self.after_operand() => {
+						self.digits(&mut iter);
+						self.add(TT::Float);
+					},
+					_ => self.add(TT::Dot),
+				},
 				',' => self.add(TT::Comma),
+				'#' => self.add(TT::Hash),
+
+				'?' => self.add(TT::Question),
+
+				// a line continuation. newlines don't end statements yet, so
+				// it's skipped along with the line break it escapes
+				'\\' => match iter.peek() {
+					Some('\n') => self.advance(&mut iter),
+					Some('\r') => {
+						self.advance(&mut iter);
+						if iter.peek() == Some(&'\n') {
+							self.advance(&mut iter);
+						}
+					}
+					_ => self.report.error_at(
+						self.span(),
+						"'\\' must be at the end of a line to continue it".to_string(),
+					),
+				},
 
 				':' => self.add(TT::Colon),
 				';' => self.add(TT::SemiColon),
+
+				'"' => {
+					if !self.quoted(&mut iter, '"') {
+						self.report.error_at(self.opener(1), "unterminated string started here".to_string());
+					} else if self.escapes().is_some() {
+						self.add(TT::String);
+					}
+				},
+				'\'' if self.label_prefix(&iter) => {
+					while iter.peek().is_some_and(|c| c.is_alphanumeric() || *c == '_') {
+						self.advance(&mut iter);
+					}
+					self.add(TT::Label);
+				},
+				'\'' => {
+					if !self.quoted(&mut iter, '\'') {
+						self.report.error_at(self.opener(1), "unterminated character literal started here".to_string());
+					} else if let Some(value) = self.escapes() {
+						if value.chars().count() == 1 {
+							self.add(TT::Char);
+						} else {
+							self.report.error_at(self.span(), "character literal must contain exactly one character".to_string());
+						}
+					}
+				},
 				
 				_ => {
 					if c.is_whitespace() {
 						// ignore
-					} else if c.is_numeric() {
-
-						while let Some(c) = iter.peek() {
-							if !c.is_numeric() {
-								break;
-							}
+					} else if let Some(radix) = self.radix_prefix(c, &iter) {
+						self.advance(&mut iter);
+						while iter.peek().is_some_and(|c| c.is_digit(radix)) {
 							self.advance(&mut iter);
 						}
-						if iter.peek() == Some(&'.') {
+						self.suffix(&mut iter, TT::Integer);
+					} else if c.is_numeric() {
+						self.digits(&mut iter);
+						// `1..` is an integer followed by a range, not a float, and
+						// neither is `0.1` in `t.0.1`
+						let mut ahead = iter.clone();
+						let float = ahead.next() == Some('.') && ahead.peek() != Some(&'.')
+							&& self.last() != Some(TT::Dot);
+						if float {
 							self.advance(&mut iter);
-							while let Some(c) = iter.peek() {
-								if !c.is_numeric() {
-									break;
-								}
-								self.advance(&mut iter);
-							}
-							self.add(TT::Float);
+							self.digits(&mut iter);
+							self.suffix(&mut iter, TT::Float);
 						} else {
-							self.add(TT::Integer);
+							self.suffix(&mut iter, TT::Integer);
 						}
 
+					} else if c == 'r' && self.raw_prefix(&iter) {
+						self.raw_string(&mut iter);
+					} else if c == 'r' && self.raw_identifier_prefix(&iter) {
+						// always an identifier, even when the name is a keyword
+						self.advance(&mut iter);
+						while iter.peek().is_some_and(|c| is_identifier_char(*c)) {
+							self.advance(&mut iter);
+						}
+						self.identifier();
 					} else if c.is_alphabetic() {
-						while let Some(c) = iter.peek() {
-							if !c.is_alphanumeric() {
-								break;
-							}
+						while iter.peek().is_some_and(|c| is_identifier_char(*c)) {
 							self.advance(&mut iter);
 						}
-						let check = &self.src[self.start..self.current];
-						match check {
-							"true" => self.add(TT::True),
-							"false" => self.add(TT::False),
-							"if" => self.add(TT::If),
-							"else" => self.add(TT::Else),
-							"let" => self.add(TT::Let),
-							"mut" => self.add(TT::Mut),
-							"fn" => self.add(TT::Fn),
-							_ => self.add(TT::Identifier),
+						match keyword(&self.src[self.start..self.current]) {
+							Some(kind) => self.add(kind),
+							None => self.identifier(),
 						}
 					} else {
 						self.report.error(format!("unknown character '{}' at {}", c, self.start));
@@ -217,30 +887,231 @@ impl<'a> Tokenize<'a> {
 		}
 
 		self.eof();
+	}
 
-		if self.report.ok() {
-			Ok(TokenStream {
-				src: self.src,
-				tokens: self.tokens,
-			})
-		} else {
-			Err(self.report)
+	fn span(&self) -> Span {
+		Span::new(self.start as u32, self.current as u32)
+	}
+
+	// the first `len` bytes of the current token, which for something left
+	// unterminated is the delimiter that opened it
+	fn opener(&self, len: usize) -> Span {
+		Span::new(self.start as u32, (self.start + len) as u32)
+	}
+
+	fn identifier(&mut self) {
+		let name = normalize(identifier_name(&self.src[self.start..self.current]));
+		let len = name.chars().count();
+		match self.options.max_identifier_len {
+			Some(max) if len > max => {
+				self.report.error_at(
+					self.span(),
+					format!("identifier is {} characters long, the maximum is {}", len, max),
+				);
+			}
+			_ => {
+				#[allow(unused_mut)]
+				let mut token = Token::new(TT::Identifier, (self.start as u32, self.current as u32), self.pos);
+				#[cfg(feature = "unicode-normalization")]
+				if let Cow::Owned(name) = name {
+					token.name = Some(name.into());
+				}
+				self.push(token);
+			}
 		}
 	}
 
 	fn add(&mut self, kind: TT) {
-		self.tokens.push(Token::new(kind, (self.start as u32, self.current as u32)));
+		self.push(Token::new(kind, (self.start as u32, self.current as u32), self.pos));
 	}
 
+	// the end of input sits just past the last character, so errors about
+	// it point at the end of the source
 	fn eof(&mut self) {
-		self.tokens.push(Token::new(TT::Eof, (0, 0)));
+		let end = self.src.len() as u32;
+		self.push(Token::new(TT::Eof, (end, end), (self.line, self.col)));
+	}
+
+	fn push(&mut self, token: Token) {
+		if !token.kind.is_trivia() {
+			self.last = Some(token.kind);
+		}
+		self.count += 1;
+		if self.keep {
+			self.tokens.push(token);
+		}
+	}
+
+}
+
+
+/// the keyword spelled `text`, if it is one
+pub fn keyword(text: &str) -> Option<TT> {
+	Some(match text {
+		"true" => TT::True,
+		"false" => TT::False,
+		"nil" => TT::Nil,
+		"if" => TT::If,
+		"else" => TT::Else,
+		"let" => TT::Let,
+		"const" => TT::Const,
+		"mut" => TT::Mut,
+		"fn" => TT::Fn,
+		"loop" => TT::Loop,
+		"repeat" => TT::Repeat,
+		"while" => TT::While,
+		"break" => TT::Break,
+		"continue" => TT::Continue,
+		"export" => TT::Export,
+		"type" => TT::Type,
+		"enum" => TT::Enum,
+		"where" => TT::Where,
+		"for" => TT::For,
+		"struct" => TT::Struct,
+		"module" => TT::Module,
+		_ => return None,
+	})
+}
+
+// whether `c` can continue an identifier. with the `unicode-normalization`
+// feature that includes combining marks, so a decomposed `é` is one name
+fn is_identifier_char(c: char) -> bool {
+	#[cfg(feature = "unicode-normalization")]
+	if unicode_normalization::char::is_combining_mark(c) {
+		return true;
+	}
+	c.is_alphanumeric()
+}
+
+// identifiers are compared in NFC with the `unicode-normalization` feature,
+// so canonically equal spellings are the same name. the text is only
+// copied if it isn't in NFC already
+#[cfg(feature = "unicode-normalization")]
+fn normalize(text: &str) -> Cow<'_, str> {
+	use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+	if is_nfc_quick(text.chars()) == IsNormalized::Yes {
+		return Cow::Borrowed(text);
+	}
+	let normalized = text.nfc().collect::<String>();
+	if normalized == text {
+		Cow::Borrowed(text)
+	} else {
+		Cow::Owned(normalized)
+	}
+}
+
+#[cfg(not(feature = "unicode-normalization"))]
+fn normalize(text: &str) -> Cow<'_, str> {
+	Cow::Borrowed(text)
+}
+
+/// the name an identifier's text refers to, which is the text itself except
+/// for raw identifiers like `r#if`
+pub fn identifier_name(text: &str) -> &str {
+	text.strip_prefix("r#").unwrap_or(text)
+}
+
+// the radix of a number literal, and how long its prefix is. like in the
+// lexer, a prefix only counts if a digit follows it
+fn radix(text: &str) -> (usize, u32) {
+	let radix = match text.get(..2) {
+		Some("0x") => 16,
+		Some("0o") => 8,
+		Some("0b") => 2,
+		_ => return (0, 10),
+	};
+	match text[2..].chars().next() {
+		Some(c) if c.is_digit(radix) => (2, radix),
+		_ => (0, 10),
 	}
+}
+
+/// the byte offset each line of `src` starts at, in order. line breaks are
+/// the same as the lexer's, so a `\r\n` is one break
+pub fn line_starts(src: &str) -> Vec<u32> {
+	let mut starts = vec![0];
+	let mut iter = src.char_indices().peekable();
+	while let Some((i, c)) = iter.next() {
+		match c {
+			'\r' if iter.peek().is_some_and(|(_, c)| *c == '\n') => (),
+			'\n' | '\r' | '\u{2028}' | '\u{2029}' => starts.push((i + c.len_utf8()) as u32),
+			_ => (),
+		}
+	}
+	starts
+}
+
+/// the 1-based line and column, in chars, of a byte offset into `src`, where
+/// `line_starts` comes from [`line_starts`]. the line is found with a binary
+/// search, so this is cheap to call for every diagnostic
+pub fn location(src: &str, line_starts: &[u32], offset: u32) -> (u32, u32) {
+	let offset = offset.min(src.len() as u32);
+	let line = line_starts.partition_point(|v| *v <= offset);
+	let start = line_starts[line - 1] as usize;
+	// like in the lexer, a byte order mark doesn't take up a column
+	let text = &src[start..offset as usize];
+	let text = if start == 0 { text.strip_prefix('\u{FEFF}').unwrap_or(text) } else { text };
+	(line as u32, text.chars().count() as u32 + 1)
+}
+
+/// the type suffixes a number literal can end with
+pub const SUFFIXES: &[&str] = &["i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "f32", "f64"];
+
+/// splits a number literal's text into the number and its type suffix, so
+/// `10i64` is `("10", "i64")`. the suffix is empty if there isn't one
+pub fn split_suffix(text: &str) -> (&str, &str) {
+	let (prefix, radix) = radix(text);
+	let end = text[prefix..].find(|c: char| !(c.is_digit(radix) || c == '.'))
+		.map_or(text.len(), |v| prefix + v);
+	text.split_at(end)
+}
+
+/// the value of an integer literal's text, without any suffix. see
+/// [`TokenStream::int_value`]
+pub fn int_value(text: &str) -> Option<u128> {
+	let text = split_suffix(text).0;
+	let (prefix, radix) = radix(text);
+	u128::from_str_radix(&text[prefix..], radix).ok()
+}
 
+/// the value of a float literal's text, without any suffix
+pub fn float_value(text: &str) -> Option<f64> {
+	split_suffix(text).0.parse().ok()
 }
 
 
 pub fn tokenize<'a>(src: &'a str) -> Result<TokenStream<'a>, Report> {
-	Tokenize::new(src).build()
+	tokenize_with(src, TokenizeOptions::default())
+}
+
+pub fn tokenize_with<'a>(src: &'a str, options: TokenizeOptions) -> Result<TokenStream<'a>, Report> {
+	let mut tokens = Vec::new();
+	lex(src, options, &mut tokens)?;
+	Ok(TokenStream::new(src, tokens))
+}
+
+/// tokenizes into `buf`, replacing its contents. this lets callers lexing
+/// many small sources reuse one allocation
+pub fn tokenize_into(src: &str, buf: &mut Vec<Token>) -> Result<(), Report> {
+	lex(src, TokenizeOptions::default(), buf)
+}
+
+/// the number of tokens `tokenize` would produce, including the final
+/// [`TT::Eof`], without storing any of them. meant for measuring the lexer
+/// on its own
+pub fn tokenize_count(src: &str) -> Result<usize, Report> {
+	let mut tokenize = Tokenize::new(src, TokenizeOptions::default(), Vec::new());
+	tokenize.keep = false;
+	tokenize.scan();
+	tokenize.report.into_result(tokenize.count)
+}
+
+fn lex(src: &str, options: TokenizeOptions, buf: &mut Vec<Token>) -> Result<(), Report> {
+	buf.clear();
+	let (tokens, report) = Tokenize::new(src, options, std::mem::take(buf)).build();
+	*buf = tokens;
+	report.into_result(())
 }
 
 
@@ -250,19 +1121,35 @@ mod test {
 
 	#[test]
 	fn symbols() {
-		let src = "+ - * / = ==";
+		let src = "+ - * / % = ==";
 		let tokens = tokenize(src).unwrap();
 		assert_eq!(
 			tokens.iter().map(|v| v.kind).collect::<Vec<_>>(),
 			vec![
 				TT::Plus, TT::Minus,
-				TT::Star, TT::Slash,
+				TT::Star, TT::Slash, TT::Percent,
 				TT::Equal, TT::EqualEqual,
 				TT::Eof,
 			],
 		);
 	}
 
+	#[test]
+	fn comparisons() {
+		let src = "! != < <= > >= =>";
+		let tokens = tokenize(src).unwrap();
+		assert_eq!(
+			tokens.iter().map(|v| v.kind).collect::<Vec<_>>(),
+			vec![
+				TT::Bang, TT::BangEqual,
+				TT::Lesser, TT::LesserEqual,
+				TT::Greater, TT::GreaterEqual,
+				TT::EqualGreater,
+				TT::Eof,
+			],
+		);
+	}
+
 	#[test]
 	fn numbers() {
 		let src = "1 10 100 1. 1.0 100.000";
@@ -286,5 +1173,414 @@ mod test {
 			vec!["+", "-", "*", "/", "100", "1", "1.0", "1.", "10.00", ""],
 		);
 	}
+
+	#[test]
+	fn max_identifier_len() {
+		let options = TokenizeOptions { max_identifier_len: Some(5), ..Default::default() };
+		assert!(tokenize_with("abcde", options.clone()).is_ok());
+		assert!(tokenize_with("while", options.clone()).is_ok());
+
+		let report = tokenize_with("1 abcdef", options).unwrap_err();
+		assert_eq!(report.errors()[0].message, "identifier is 6 characters long, the maximum is 5");
+		assert_eq!(report.errors()[0].span, Some(Span::new(2, 8)));
+
+		assert!(tokenize("abcdef").is_ok());
+	}
+
+	#[cfg(feature = "unicode-normalization")]
+	#[test]
+	fn normalized_identifiers() {
+		// a precomposed `é`, then an `e` with a combining acute accent
+		let src = "let caf\u{e9} = 1; cafe\u{301} + r#cafe\u{301}";
+		let tokens = tokenize(src).unwrap();
+		let names = tokens.iter()
+			.filter(|v| v.kind == TT::Identifier)
+			.map(|v| v.get(src))
+			.collect::<Vec<_>>();
+		assert_eq!(names, vec!["caf\u{e9}"; 3]);
+		// spans still cover the source as written
+		assert_eq!(tokens.get(5).span(), Span::new(15, 21));
+
+		let ast = crate::parse(src, &tokens).unwrap();
+		assert_eq!(crate::eval::eval(&ast).unwrap(), crate::eval::Value::Int(2));
+	}
+
+	#[test]
+	fn labels() {
+		let src = "'outer 'a' '_x: '\\n'";
+		let tokens = tokenize(src).unwrap();
+		assert_eq!(
+			tokens.iter().map(|v| v.kind).collect::<Vec<_>>(),
+			vec![TT::Label, TT::Char, TT::Label, TT::Colon, TT::Char, TT::Eof],
+		);
+		assert_eq!(tokens.str_from(tokens.get(0)), "'outer");
+	}
+
+	#[test]
+	fn lines() {
+		for src in ["1\r\n2", "1\n2", "1\r2"] {
+			let tokens = tokenize(src).unwrap();
+			assert_eq!((tokens.get(0).line(), tokens.get(0).col()), (1, 1), "{:?}", src);
+			assert_eq!((tokens.get(1).line(), tokens.get(1).col()), (2, 1), "{:?}", src);
+		}
+
+		let src = "a\r\n\r\n  b + \"\n\" c";
+		let tokens = tokenize(src).unwrap();
+		assert_eq!((tokens.get(1).line(), tokens.get(1).col()), (3, 3));
+		assert_eq!((tokens.get(3).line(), tokens.get(3).col()), (3, 7));
+		assert_eq!((tokens.get(4).line(), tokens.get(4).col()), (4, 3));
+	}
+
+	#[test]
+	fn kind_counts() {
+		let src = "let x = 1; let y = x + 2.0; if x == 1 { y }";
+		let tokens = tokenize(src).unwrap();
+		let counts = tokens.kind_counts();
+		assert_eq!(counts[&TT::Let], 2);
+		assert_eq!(counts[&TT::Identifier], 5);
+		assert_eq!(counts[&TT::Integer], 2);
+		assert_eq!(counts[&TT::Float], 1);
+		assert_eq!(counts[&TT::Eof], 1);
+		assert_eq!(counts.get(&TT::While), None);
+	}
+
+	#[test]
+	fn predicates() {
+		assert!(TT::Integer.is_literal());
+		assert!(TT::False.is_literal());
+		assert!(!TT::Identifier.is_literal());
+
+		assert!(TT::Let.is_keyword());
+		assert!(!TT::Identifier.is_keyword());
+
+		assert!(TT::Star.is_binary_op());
+		assert!(TT::LesserEqual.is_binary_op());
+		assert!(!TT::Bang.is_binary_op());
+
+		assert!(TT::Bang.is_unary_op());
+		assert!(TT::Minus.is_unary_op() && TT::Minus.is_binary_op());
+		assert!(!TT::Plus.is_unary_op());
+	}
+
+	#[test]
+	fn escapes() {
+		let src = r#""\x41" "\u{1F600}" 'a' '\x41' "\u{41}b\n""#;
+		let tokens = tokenize(src).unwrap();
+		assert_eq!(tokens.string_value(tokens.get(0)).as_deref(), Some("A"));
+		assert_eq!(tokens.string_value(tokens.get(1)).as_deref(), Some("\u{1F600}"));
+		assert_eq!(tokens.char_value(tokens.get(2)), Some('a'));
+		assert_eq!(tokens.char_value(tokens.get(3)), Some('A'));
+		assert_eq!(tokens.string_value(tokens.get(4)).as_deref(), Some("Ab\n"));
+
+		// the raw span is left alone
+		assert_eq!(tokens.str_from(tokens.get(0)), r#""\x41""#);
+	}
+
+	#[test]
+	fn raw_strings() {
+		let src = "r\"a\\nb\" r#\"say \"hi\"\"# r##\"a\"#b\"## r\"one\ntwo\" r";
+		let tokens = tokenize(src).unwrap();
+		assert_eq!(tokens.raw_string_value(tokens.get(0)), Some("a\\nb"));
+		assert_eq!(tokens.raw_string_value(tokens.get(1)), Some("say \"hi\""));
+		assert_eq!(tokens.raw_string_value(tokens.get(2)), Some("a\"#b"));
+		assert_eq!(tokens.raw_string_value(tokens.get(3)), Some("one\ntwo"));
+		assert_eq!(tokens.get(4).kind, TT::Identifier);
+
+		let report = tokenize("1 r#\"abc\"").unwrap_err();
+		assert_eq!(report.errors()[0].message, "unterminated raw string started here");
+	}
+
+	#[test]
+	fn escapes_malformed() {
+		let src = r#"1 "a\u{zzz}""#;
+		let report = tokenize(src).unwrap_err();
+		assert_eq!(report.errors()[0].span, Some(Span::new(4, 7)));
+
+		for src in [r#""\x4""#, r#""\xff""#, r#""\u{110000}""#, r#""\u{41""#, r#""\q""#, "'ab'", "\"abc"] {
+			assert!(tokenize(src).is_err(), "{}", src);
+		}
+	}
+
+	#[test]
+	fn comments() {
+		let src = "a // one\nb /* two\nthree */ c / d";
+
+		let tokens = tokenize(src).unwrap();
+		let kinds = tokens.iter().map(|v| v.kind).collect::<Vec<_>>();
+		assert_eq!(kinds, vec![TT::Identifier, TT::Identifier, TT::Identifier, TT::Slash, TT::Identifier, TT::Eof]);
+
+		let options = TokenizeOptions { keep_comments: true, ..Default::default() };
+		let tokens = tokenize_with(src, options).unwrap();
+		let comments = tokens.iter()
+			.filter(|v| v.kind.is_trivia())
+			.map(|v| (v.kind, v.get(src)))
+			.collect::<Vec<_>>();
+		assert_eq!(comments, vec![
+			(TT::LineComment, "// one"),
+			(TT::BlockComment, "/* two\nthree */"),
+		]);
+		assert_eq!(tokens.get(4).line(), 3);
+
+		let report = tokenize("a /* b").unwrap_err();
+		assert_eq!(report.errors()[0].message, "unterminated block comment started here");
+	}
+
+	#[test]
+	fn into_buffer() {
+		let mut buf = Vec::new();
+		for src in ["let x = 1 + 2", "fn (a) => a", "x"] {
+			tokenize_into(src, &mut buf).unwrap();
+			assert_eq!(buf, tokenize(src).unwrap().tokens);
+		}
+
+		assert!(tokenize_into("\"abc", &mut buf).is_err());
+		tokenize_into("", &mut buf).unwrap();
+		assert_eq!(buf.len(), 1);
+	}
+
+	#[test]
+	fn ranges() {
+		let src = "1..2 1.5 a[..]";
+		let tokens = tokenize(src).unwrap();
+		let kinds = tokens.iter().map(|v| v.kind).collect::<Vec<_>>();
+		assert_eq!(kinds, vec![
+			TT::Integer, TT::DotDot, TT::Integer, TT::Float,
+			TT::Identifier, TT::LBracket, TT::DotDot, TT::RBracket, TT::Eof,
+		]);
+	}
+
+	#[test]
+	fn line_continuation() {
+		for src in ["1 + \\\n2", "1 + \\\r\n2"] {
+			let tokens = tokenize(src).unwrap();
+			let kinds = tokens.iter().map(|v| v.kind).collect::<Vec<_>>();
+			assert_eq!(kinds, vec![TT::Integer, TT::Plus, TT::Integer, TT::Eof]);
+			assert_eq!(tokens.get(2).line(), 2);
+		}
+
+		let report = tokenize("1 \\ 2").unwrap_err();
+		assert_eq!(report.errors()[0].message, "'\\' must be at the end of a line to continue it");
+		assert_eq!(report.errors()[0].span, Some(Span::new(2, 3)));
+	}
+
+	#[test]
+	fn dots() {
+		let src = ". .. ... ....";
+		let tokens = tokenize(src).unwrap();
+		let kinds = tokens.iter().map(|v| v.kind).collect::<Vec<_>>();
+		assert_eq!(kinds, vec![TT::Dot, TT::DotDot, TT::DotDotDot, TT::DotDotDot, TT::Dot, TT::Eof]);
+	}
+
+	#[test]
+	fn floats() {
+		for (src, kinds) in [
+			(".", vec![TT::Dot]),
+			("..", vec![TT::DotDot]),
+			("1.", vec![TT::Float]),
+			(".5", vec![TT::Float]),
+			("1..2", vec![TT::Integer, TT::DotDot, TT::Integer]),
+			("..5", vec![TT::DotDot, TT::Integer]),
+			("- .5", vec![TT::Minus, TT::Float]),
+			("t.0", vec![TT::Identifier, TT::Dot, TT::Integer]),
+			("(t).0.1", vec![TT::LParen, TT::Identifier, TT::RParen, TT::Dot, TT::Integer, TT::Dot, TT::Integer]),
+		] {
+			let tokens = tokenize(src).unwrap();
+			let mut found = tokens.iter().map(|v| v.kind).collect::<Vec<_>>();
+			assert_eq!(found.pop(), Some(TT::Eof));
+			assert_eq!(found, kinds, "{}", src);
+		}
+	}
+
+	#[test]
+	fn source_too_large() {
+		let mut lexer = Tokenize::new("let x = 1", TokenizeOptions::default(), Vec::new());
+		lexer.max_len = 4;
+		let (tokens, report) = lexer.build();
+		assert_eq!(report.errors()[0].message, "source too large: 9 bytes, the maximum is 4");
+		assert_eq!(tokens.iter().map(|v| v.kind).collect::<Vec<_>>(), vec![TT::Eof]);
+	}
+
+	#[test]
+	fn slice_between() {
+		let src = "1 + 2 * /* three */ 3";
+		let tokens = tokenize(src).unwrap();
+		let first = tokens.get(0);
+		let last = tokens.get(tokens.tokens.len() - 2);
+		assert_eq!(tokens.slice_between(first, last), src);
+		assert_eq!(tokens.slice_between(tokens.get(2), tokens.get(2)), "2");
+		assert!(std::panic::catch_unwind(|| tokens.slice_between(last, first)).is_err());
+	}
+
+	#[test]
+	fn radix() {
+		let src = "0xFF 0o17 0b101 10";
+		let tokens = tokenize(src).unwrap();
+		let found = tokens.iter()
+			.map(|v| (v.kind, tokens.int_value(v)))
+			.collect::<Vec<_>>();
+		assert_eq!(found, vec![
+			(TT::Integer, Some(255)),
+			(TT::Integer, Some(15)),
+			(TT::Integer, Some(5)),
+			(TT::Integer, Some(10)),
+			(TT::Eof, None),
+		]);
+
+		// without a digit, the `x` and `b2` are just letters after a `0`
+		for src in ["0x", "0b2"] {
+			let report = tokenize(src).unwrap_err();
+			assert_eq!(report.errors()[0].message, "invalid number literal", "{}", src);
+		}
+	}
+
+	#[test]
+	fn count() {
+		for src in [
+			"",
+			"let x = 1 // one\nx.0.1 + .5",
+			"t.0 .5 0x1f 'a' \"s\" r#\"raw\"# 'outer",
+		] {
+			assert_eq!(tokenize_count(src).unwrap(), tokenize(src).unwrap().iter().count(), "{}", src);
+		}
+		assert!(tokenize_count("\"open").is_err());
+	}
+
+	#[test]
+	fn reserved() {
+		for kind in [
+			TT::True, TT::False, TT::Nil,
+			TT::Let, TT::Const, TT::Mut,
+			TT::If, TT::Else, TT::For, TT::While, TT::Loop, TT::Repeat,
+			TT::Break, TT::Continue,
+			TT::Export, TT::Type, TT::Enum, TT::Where, TT::Struct, TT::Module, TT::Fn,
+		] {
+			assert!(kind.is_keyword());
+			let src = kind.symbol().unwrap();
+			let tokens = tokenize(src).unwrap();
+			assert_eq!(tokens.iter().map(|v| v.kind).collect::<Vec<_>>(), vec![kind, TT::Eof], "{}", src);
+		}
+	}
+
+	#[test]
+	fn raw_identifier() {
+		let src = "if r#if r#x r#\"s\"# r";
+		let tokens = tokenize(src).unwrap();
+		assert_eq!(
+			tokens.iter().map(|v| v.kind).collect::<Vec<_>>(),
+			vec![TT::If, TT::Identifier, TT::Identifier, TT::RawString, TT::Identifier, TT::Eof],
+		);
+		assert_eq!(tokens.str_from(tokens.get(1)), "if");
+		assert_eq!(tokens.get(1).span(), Span::new(3, 7));
+		assert_eq!(tokens.str_from(tokens.get(2)), "x");
+		assert_eq!(tokens.str_from(tokens.get(4)), "r");
+		assert_eq!(keyword("if"), Some(TT::If));
+		assert_eq!(keyword("r#if"), None);
+	}
+
+	#[test]
+	fn suffix() {
+		let src = "10i64 2.5f64 0xffu8 1..2 t.0";
+		let tokens = tokenize(src).unwrap();
+		assert_eq!(
+			tokens.iter().map(|v| v.get(src)).collect::<Vec<_>>(),
+			vec!["10i64", "2.5f64", "0xffu8", "1", "..", "2", "t", ".", "0", ""],
+		);
+		assert_eq!(split_suffix("10i64"), ("10", "i64"));
+		assert_eq!(split_suffix("0xffu8"), ("0xff", "u8"));
+		assert_eq!(split_suffix(".5"), (".5", ""));
+		assert_eq!(tokens.int_value(tokens.get(2)), Some(255));
+		assert_eq!(tokens.float_value(tokens.get(1)), Some(2.5));
+	}
+
+	#[test]
+	fn location() {
+		let src = "let x\r\n  é = 1\nfoo";
+		let tokens = tokenize(src).unwrap();
+		assert_eq!(tokens.line_starts(), &[0, 7, 16]);
+		for (offset, expected) in [(0, (1, 1)), (4, (1, 5)), (7, (2, 1)), (12, (2, 5)), (14, (2, 7)), (16, (3, 1)), (19, (3, 4))] {
+			assert_eq!(tokens.location(offset), expected, "{}", offset);
+		}
+
+		// every token agrees with its own position
+		for token in tokens.iter().filter(|v| v.kind != TT::Eof) {
+			assert_eq!(tokens.location(token.span().start), (token.line(), token.col()));
+		}
+	}
+
+	#[test]
+	fn byte_order_mark() {
+		let src = "\u{FEFF}let x";
+		let tokens = tokenize(src).unwrap();
+		assert_eq!(tokens.get(0).kind, TT::Let);
+		assert_eq!(tokens.get(0).span(), Span::new(3, 6));
+		assert_eq!((tokens.get(0).line(), tokens.get(0).col()), (1, 1));
+
+		// only at the very start
+		assert!(tokenize("x \u{FEFF}").is_err());
+	}
+
+	#[test]
+	fn shebang() {
+		let src = "#!/usr/bin/env myano\n1 + 1";
+		let tokens = tokenize(src).unwrap();
+		let found = tokens.iter()
+			.map(|v| (v.kind, v.get(src), v.span(), v.line(), v.col()))
+			.collect::<Vec<_>>();
+		assert_eq!(found, vec![
+			(TT::Integer, "1", Span::new(21, 22), 2, 1),
+			(TT::Plus, "+", Span::new(23, 24), 2, 3),
+			(TT::Integer, "1", Span::new(25, 26), 2, 5),
+			(TT::Eof, "", Span::new(26, 26), 2, 6),
+		]);
+		assert_eq!(tokenize("#!").unwrap().get(0).kind, TT::Eof);
+
+		// after a byte order mark
+		let src = "\u{FEFF}#!/usr/bin/env myano\nx";
+		let tokens = tokenize(src).unwrap();
+		assert_eq!(tokens.get(0).kind, TT::Identifier);
+		assert_eq!((tokens.get(0).line(), tokens.get(0).col()), (2, 1));
+
+		// anywhere else it's a `#` and a `!`
+		let tokens = tokenize("1\n#!x").unwrap();
+		assert_eq!(tokens.get(1).kind, TT::Hash);
+		assert_eq!(tokens.get(2).kind, TT::Bang);
+	}
+
+	#[test]
+	fn line_separators() {
+		let src = "a\u{2028}b // c\u{2029}d";
+		let tokens = tokenize(src).unwrap();
+		let found = tokens.iter()
+			.filter(|v| v.kind == TT::Identifier)
+			.map(|v| (v.get(src), v.line(), v.col()))
+			.collect::<Vec<_>>();
+		assert_eq!(found, vec![("a", 1, 1), ("b", 2, 1), ("d", 3, 1)]);
+	}
+
+	#[test]
+	fn unterminated() {
+		for (src, message, span) in [
+			("x = \"abc\ndef", "unterminated string started here", Span::new(4, 5)),
+			("1 r##\"abc\"#", "unterminated raw string started here", Span::new(2, 6)),
+			("a /* b\nc", "unterminated block comment started here", Span::new(2, 4)),
+			("'\\", "unterminated character literal started here", Span::new(0, 1)),
+		] {
+			let report = tokenize(src).unwrap_err();
+			assert_eq!(report.errors()[0].message, message, "{}", src);
+			assert_eq!(report.errors()[0].span, Some(span), "{}", src);
+		}
+	}
+
+	#[test]
+	fn glued_number() {
+		for (src, span) in [("123abc", Span::new(0, 6)), ("x = 2.5e3", Span::new(4, 9)), ("10q9 + 1", Span::new(0, 4))] {
+			let report = tokenize(src).unwrap_err();
+			assert_eq!(report.errors()[0].message, "invalid number literal", "{}", src);
+			assert_eq!(report.errors()[0].span, Some(span), "{}", src);
+		}
+		for src in ["123", "10i64", "1u8 + 2.5f64", "3.14f32"] {
+			assert!(tokenize(src).is_ok(), "{}", src);
+		}
+	}
 }
 